@@ -0,0 +1,259 @@
+use super::parser::{self, Direct, Expr, Line, Operand, Statement, Term};
+use super::AsmError;
+use crate::cpu::{DataWidth, OperandKind, INSTRUCTIONS};
+use crate::devices::MAX_RAM_SIZE;
+use std::collections::HashMap;
+
+/// Label addresses depend on the size of earlier instructions, which in turn depend on the values
+/// of any labels they reference. Passes are repeated until the addresses stop changing.
+const MAX_PASSES: usize = 16;
+
+/// Assembles source code into a memory image that can be loaded at address zero
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let lines = parser::parse(source)?;
+    let mut symbols = HashMap::new();
+    for _ in 0..MAX_PASSES {
+        let pass = Pass::run(&lines, &symbols, false)?;
+        if pass.symbols == symbols {
+            return Ok(Pass::run(&lines, &symbols, true)?.output);
+        }
+        symbols = pass.symbols;
+    }
+    Err(AsmError::new(
+        0,
+        "label addresses never settled".to_string(),
+    ))
+}
+
+struct Pass<'a> {
+    /// the symbols from the previous pass, used to resolve forward references
+    previous: &'a HashMap<String, u32>,
+    symbols: HashMap<String, u32>,
+    output: Vec<u8>,
+    /// whether unresolved symbols are an error. Otherwise they're assumed to need a full word.
+    strict: bool,
+}
+
+impl<'a> Pass<'a> {
+    fn run(
+        lines: &[Line],
+        previous: &'a HashMap<String, u32>,
+        strict: bool,
+    ) -> Result<Pass<'a>, AsmError> {
+        let mut pass = Pass {
+            previous,
+            symbols: HashMap::new(),
+            output: Vec::new(),
+            strict,
+        };
+        for line in lines {
+            pass.statement(&line.statement)
+                .map_err(|message| AsmError::new(line.number, message))?;
+        }
+        Ok(pass)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), String> {
+        match statement {
+            Statement::Label(name) => self.define(name, Some(self.output.len() as u32)),
+            Statement::Constant(name, value) => {
+                let value = self.resolve(value)?;
+                self.define(name, value)
+            }
+            Statement::Instruction(mnemonic, operands) => self.instruction(mnemonic, operands),
+            Statement::Data(width, values) => {
+                for value in values {
+                    let value = self.resolve(value)?.unwrap_or(0);
+                    check_fits(value, *width)?;
+                    self.push_width(*width, value);
+                }
+                Ok(())
+            }
+            Statement::Bytes(bytes) => {
+                self.output.extend_from_slice(bytes);
+                Ok(())
+            }
+            Statement::Address(address) => {
+                if let Some(address) = self.resolve(address)? {
+                    let address = address as usize;
+                    if address < self.output.len() {
+                        return Err(format!(
+                            "`#addr` can't move backwards from 0x{:x} to 0x{:x}",
+                            self.output.len(),
+                            address
+                        ));
+                    }
+                    // images are loaded into RAM, so anything past it is almost certainly a typo
+                    if address >= MAX_RAM_SIZE {
+                        return Err(format!(
+                            "`#addr` 0x{:x} isn't below the end of RAM at 0x{:x}",
+                            address, MAX_RAM_SIZE
+                        ));
+                    }
+                    self.output.resize(address, 0);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn define(&mut self, name: &str, value: Option<u32>) -> Result<(), String> {
+        if self.symbols.contains_key(name) {
+            return Err(format!("`{}` is already defined", name));
+        }
+        if let Some(value) = value {
+            self.symbols.insert(name.to_string(), value);
+        }
+        Ok(())
+    }
+
+    /// Evaluates an expression, returning `None` if it references a symbol that isn't known yet
+    fn resolve(&self, expr: &Expr) -> Result<Option<u32>, String> {
+        let mut total = 0u32;
+        for (negative, term) in &expr.terms {
+            let value = match term {
+                Term::Number(value) => *value,
+                // negating a float flips its sign bit rather than subtracting its bits
+                Term::Float(value) if *negative => {
+                    total = total.wrapping_add((-value).to_bits());
+                    continue;
+                }
+                Term::Float(value) => value.to_bits(),
+                Term::Symbol(name) => match self.symbols.get(name).or(self.previous.get(name)) {
+                    Some(value) => *value,
+                    None if self.strict => return Err(format!("undefined symbol `{}`", name)),
+                    None => return Ok(None),
+                },
+            };
+            total = if *negative {
+                total.wrapping_sub(value)
+            } else {
+                total.wrapping_add(value)
+            };
+        }
+        Ok(Some(total))
+    }
+
+    fn instruction(&mut self, mnemonic: &str, operands: &[Operand]) -> Result<(), String> {
+        // some mnemonics are shared by several instructions that differ in their operand count
+        let candidates: Vec<_> = INSTRUCTIONS
+            .iter()
+            .filter(|info| info.mnemonic == mnemonic)
+            .collect();
+        if candidates.is_empty() {
+            return Err(format!("unknown instruction `{}`", mnemonic));
+        }
+        let info = candidates
+            .iter()
+            .find(|info| info.operands.len() == operands.len())
+            .ok_or_else(|| {
+                let counts: Vec<_> = candidates
+                    .iter()
+                    .map(|info| info.operands.len().to_string())
+                    .collect();
+                format!(
+                    "`{}` takes {} operands but {} were given",
+                    mnemonic,
+                    counts.join(" or "),
+                    operands.len()
+                )
+            })?;
+
+        self.output.push(info.opcode);
+        for (operand, kind) in operands.iter().zip(info.operands.iter()) {
+            match (kind, operand) {
+                (OperandKind::Address, Operand::Immediate(address, None)) => {
+                    let address = self.resolve(address)?.unwrap_or(0);
+                    self.push_width(DataWidth::Word, address);
+                }
+                (OperandKind::Address, _) => return Err("expected an address".to_string()),
+                (OperandKind::Dest, Operand::Immediate(..)) => {
+                    return Err("an immediate can't be used as a destination".to_string())
+                }
+                _ => self.location(operand)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes an operand in the format read by `Location::decode`
+    fn location(&mut self, operand: &Operand) -> Result<(), String> {
+        match operand {
+            Operand::Immediate(value, width) => {
+                let value = self.resolve(value)?;
+                match (value, width) {
+                    (Some(value), None) if value < 0b1000_0000 => self.output.push(value as u8),
+                    _ => {
+                        let width = width.unwrap_or_else(|| smallest_width(value.unwrap_or(!0)));
+                        let value = value.unwrap_or(0);
+                        check_fits(value, width)?;
+                        self.output.push(0b1000_0000 | width_bits(width));
+                        self.push_width(width, value);
+                    }
+                }
+            }
+            Operand::Register(index) => self.output.push(0b1100_0000 | index),
+            Operand::Frame(index) => {
+                let index = self.resolve(index)?.unwrap_or(!0);
+                let width = smallest_width(index);
+                self.output.push(0b1000_0100 | width_bits(width));
+                self.push_width(width, index);
+            }
+            Operand::Indirect(direct, width) => self.indirect(0b1000_1000, direct, *width)?,
+            Operand::PostIncrement(direct, width) => self.indirect(0b1001_0000, direct, *width)?,
+            Operand::PreDecrement(direct, width) => self.indirect(0b1001_1000, direct, *width)?,
+        }
+        Ok(())
+    }
+
+    fn indirect(&mut self, mode: u8, direct: &Direct, width: DataWidth) -> Result<(), String> {
+        match direct {
+            Direct::Register(index) => {
+                self.output.push(mode | width_bits(width));
+                self.output.push(*index);
+            }
+            Direct::Frame(index) => {
+                let index = self.resolve(index)?.unwrap_or(0);
+                self.output.push(mode | 0b100 | width_bits(width));
+                self.push_width(DataWidth::Word, index);
+            }
+        }
+        Ok(())
+    }
+
+    fn push_width(&mut self, width: DataWidth, value: u32) {
+        let bytes = value.to_be_bytes();
+        self.output.extend_from_slice(&bytes[4 - width.size()..]);
+    }
+}
+
+fn width_bits(width: DataWidth) -> u8 {
+    match width {
+        DataWidth::Byte => 0b00,
+        DataWidth::Short => 0b01,
+        DataWidth::Word => 0b10,
+    }
+}
+
+fn smallest_width(value: u32) -> DataWidth {
+    if value <= DataWidth::Byte.bitmask() {
+        DataWidth::Byte
+    } else if value <= DataWidth::Short.bitmask() {
+        DataWidth::Short
+    } else {
+        DataWidth::Word
+    }
+}
+
+fn check_fits(value: u32, width: DataWidth) -> Result<(), String> {
+    if value > width.bitmask() {
+        let name = match width {
+            DataWidth::Byte => "byte",
+            DataWidth::Short => "short",
+            DataWidth::Word => "word",
+        };
+        Err(format!("0x{:x} doesn't fit in a {}", value, name))
+    } else {
+        Ok(())
+    }
+}
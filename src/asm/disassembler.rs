@@ -0,0 +1,55 @@
+use crate::cpu::{CpuResult, Instruction, Memory, ProgramCounter};
+
+pub struct Disassembly {
+    pub address: u32,
+    /// the length of the instruction in bytes
+    pub length: u32,
+    pub text: String,
+}
+
+/// Decodes the instruction at the given address using the same rules as `Cpu::tick`
+pub fn disassemble(memory: &Memory, address: u32) -> CpuResult<Disassembly> {
+    decode(memory, address).map(|(instruction, length)| Disassembly {
+        address,
+        length,
+        text: instruction.to_string(),
+    })
+}
+
+/// Decodes the instruction at the given address, returning it along with its length in bytes
+fn decode(memory: &Memory, address: u32) -> CpuResult<(Instruction, u32)> {
+    let mut pc = ProgramCounter::new(address);
+    Instruction::decode(memory, &mut pc)
+        .map(|instruction| (instruction, pc.address.wrapping_sub(address)))
+        .map_err(|panic| panic.at(address))
+}
+
+/// Disassembles up to `count` instructions starting at the given address, stopping early at the
+/// end of memory. Bytes that aren't valid instructions, or that decode to instructions the
+/// assembler can't produce, are shown as `#d8` data so the output is still valid assembly.
+/// Operands are printed without the width they were encoded with, so reassembling the output only
+/// gives back the same bytes if the image used the assembler's default encodings.
+pub fn disassemble_range(memory: &Memory, address: u32, count: usize) -> Vec<Disassembly> {
+    let mut lines = Vec::new();
+    let mut address = address;
+    while lines.len() < count {
+        let line = match decode(memory, address) {
+            Ok((instruction, length)) if instruction.assemblable() => Disassembly {
+                address,
+                length,
+                text: instruction.to_string(),
+            },
+            _ => match memory.read_byte(address) {
+                Ok(byte) => Disassembly {
+                    address,
+                    length: 1,
+                    text: format!("#d8 0x{:02x}", byte),
+                },
+                Err(_) => break,
+            },
+        };
+        address = address.wrapping_add(line.length);
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,49 @@
+//! An assembler and disassembler for the instruction encoding executed by `Cpu::tick`.
+//!
+//! ```text
+//! ; comments start with a semicolon
+//! count = 10              ; constants
+//! start:                  ; labels
+//!     move count, r0      ; immediates, registers and frame vars (`frame[2]`)
+//!     move 1.5, r2        ; float literals are converted to their bits
+//! .loop:                  ; local labels are scoped to the last global label
+//!     sub r0, 1, r0
+//!     move r0, [r1]+.b    ; indirect (`[r1]`), post-increment and pre-decrement (`-[r1]`)
+//!     jne r0, 0, .loop
+//! #d8 1, 2, 'a'           ; raw data, also `#d16`, `#d32` and `#str "text"`
+//! #addr 0x100             ; pad the output up to an address
+//! ```
+mod assembler;
+mod disassembler;
+mod parser;
+
+pub use assembler::assemble;
+pub use disassembler::{disassemble, disassemble_range, Disassembly};
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+    /// The 1-based line the error occurred on, or 0 if it isn't tied to a specific line
+    pub line: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    pub fn new(line: usize, message: String) -> AsmError {
+        AsmError { line, message }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl Error for AsmError {}
@@ -0,0 +1,428 @@
+use super::AsmError;
+use crate::cpu::DataWidth;
+
+pub struct Line {
+    pub number: usize,
+    pub statement: Statement,
+}
+
+pub enum Statement {
+    Label(String),
+    Constant(String, Expr),
+    Instruction(String, Vec<Operand>),
+    /// `#d8`, `#d16` and `#d32`
+    Data(DataWidth, Vec<Expr>),
+    /// `#str`
+    Bytes(Vec<u8>),
+    /// `#addr`
+    Address(Expr),
+}
+
+pub enum Operand {
+    Immediate(Expr, Option<DataWidth>),
+    Register(u8),
+    Frame(Expr),
+    Indirect(Direct, DataWidth),
+    PostIncrement(Direct, DataWidth),
+    PreDecrement(Direct, DataWidth),
+}
+
+pub enum Direct {
+    Register(u8),
+    Frame(Expr),
+}
+
+/// A sum of numbers and symbols, with each term being either added or subtracted
+pub struct Expr {
+    pub terms: Vec<(bool, Term)>,
+}
+
+pub enum Term {
+    Number(u32),
+    /// A float literal, which evaluates to its bits
+    Float(f32),
+    Symbol(String),
+}
+
+/// Parses assembly source into a list of statements. Local names (ones starting with a `.`) are
+/// qualified with the name of the last global label here, so the assembler never sees them.
+pub fn parse(source: &str) -> Result<Vec<Line>, AsmError> {
+    let mut lines = Vec::new();
+    let mut scope = String::new();
+    for (index, text) in source.lines().enumerate() {
+        let number = index + 1;
+        let mut cursor = Cursor {
+            text: strip_comment(text),
+            pos: 0,
+            scope: &scope,
+        };
+        let mut statements = Vec::new();
+        cursor
+            .line(&mut statements)
+            .map_err(|message| AsmError::new(number, message))?;
+        for statement in statements {
+            if let Statement::Label(name) = &statement {
+                if !name.contains('.') {
+                    scope = name.clone();
+                }
+            }
+            lines.push(Line { number, statement });
+        }
+    }
+    Ok(lines)
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ';' => return &text[..i],
+            None => {}
+        }
+    }
+    text
+}
+
+struct Cursor<'a> {
+    text: &'a str,
+    pos: usize,
+    scope: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn line(&mut self, statements: &mut Vec<Statement>) -> Result<(), String> {
+        // any number of labels can prefix a line
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            match self.name() {
+                Some(name) if self.eat(':') => {
+                    let name = self.qualify(name)?;
+                    statements.push(Statement::Label(name));
+                }
+                _ => {
+                    self.pos = start;
+                    break;
+                }
+            }
+        }
+
+        if self.at_end() {
+            return Ok(());
+        }
+        let statement = if self.eat('#') {
+            self.directive()?
+        } else {
+            let start = self.pos;
+            let name = self.name().ok_or_else(|| self.unexpected())?;
+            if self.eat('=') {
+                let name = self.qualify(name)?;
+                Statement::Constant(name, self.expr()?)
+            } else {
+                self.pos = start;
+                self.instruction()?
+            }
+        };
+        self.skip_whitespace();
+        if !self.at_end() {
+            return Err(self.unexpected());
+        }
+        statements.push(statement);
+        Ok(())
+    }
+
+    fn directive(&mut self) -> Result<Statement, String> {
+        let name = self.word().ok_or_else(|| self.unexpected())?;
+        Ok(match name {
+            "d8" => Statement::Data(DataWidth::Byte, self.list(Cursor::expr)?),
+            "d16" => Statement::Data(DataWidth::Short, self.list(Cursor::expr)?),
+            "d32" => Statement::Data(DataWidth::Word, self.list(Cursor::expr)?),
+            "str" => Statement::Bytes(self.string()?),
+            "addr" => Statement::Address(self.expr()?),
+            _ => return Err(format!("unknown directive `#{}`", name)),
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Statement, String> {
+        let mnemonic = self.word().ok_or_else(|| self.unexpected())?.to_string();
+        self.skip_whitespace();
+        let operands = if self.at_end() {
+            vec![]
+        } else {
+            self.list(Cursor::operand)?
+        };
+        Ok(Statement::Instruction(mnemonic, operands))
+    }
+
+    fn list<T>(&mut self, item: fn(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let mut items = vec![item(self)?];
+        while self.eat(',') {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        self.skip_whitespace();
+        if self.rest().starts_with("-[") {
+            self.pos += 1;
+            let direct = self.bracketed_direct()?;
+            return Ok(Operand::PreDecrement(
+                direct,
+                self.width()?.unwrap_or(DataWidth::Word),
+            ));
+        }
+        if self.rest().starts_with('[') {
+            let direct = self.bracketed_direct()?;
+            let post_increment = self.eat('+');
+            let width = self.width()?.unwrap_or(DataWidth::Word);
+            return Ok(if post_increment {
+                Operand::PostIncrement(direct, width)
+            } else {
+                Operand::Indirect(direct, width)
+            });
+        }
+
+        let start = self.pos;
+        if let Some(direct) = self.direct()? {
+            return Ok(match direct {
+                Direct::Register(index) => Operand::Register(index),
+                Direct::Frame(index) => Operand::Frame(index),
+            });
+        }
+        self.pos = start;
+        let value = self.expr()?;
+        Ok(Operand::Immediate(value, self.width()?))
+    }
+
+    fn bracketed_direct(&mut self) -> Result<Direct, String> {
+        self.expect('[')?;
+        let direct = self
+            .direct()?
+            .ok_or_else(|| "expected a register or frame var".to_string())?;
+        self.expect(']')?;
+        Ok(direct)
+    }
+
+    /// Parses a register or frame var, returning `None` if neither is present
+    fn direct(&mut self) -> Result<Option<Direct>, String> {
+        self.skip_whitespace();
+        let word = match self.word() {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+        if word == "frame" && self.eat('[') {
+            let index = self.expr()?;
+            self.expect(']')?;
+            return Ok(Some(Direct::Frame(index)));
+        }
+        if word.starts_with('r') && word.len() > 1 && word[1..].chars().all(|c| c.is_ascii_digit())
+        {
+            return match word[1..].parse::<u8>() {
+                Ok(index) if index < 16 => Ok(Some(Direct::Register(index))),
+                _ => Err(format!("invalid register `{}`", word)),
+            };
+        }
+        Ok(None)
+    }
+
+    /// Parses an optional `.b`, `.s` or `.w` width suffix
+    fn width(&mut self) -> Result<Option<DataWidth>, String> {
+        if !self.eat('.') {
+            return Ok(None);
+        }
+        match self.word() {
+            Some("b") => Ok(Some(DataWidth::Byte)),
+            Some("s") => Ok(Some(DataWidth::Short)),
+            Some("w") => Ok(Some(DataWidth::Word)),
+            _ => Err("expected a width of `.b`, `.s` or `.w`".to_string()),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut terms = Vec::new();
+        let mut negative = self.eat('-');
+        loop {
+            terms.push((negative, self.term()?));
+            if self.eat('+') {
+                negative = false;
+            } else if self.eat('-') {
+                negative = true;
+            } else {
+                return Ok(Expr { terms });
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Term, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                let word = self.word().unwrap().replace('_', "");
+                if self.at_fraction() && word.chars().all(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                    let number = format!("{}.{}", word, self.word().unwrap());
+                    return number
+                        .parse()
+                        .map(Term::Float)
+                        .map_err(|_| format!("invalid number `{}`", number));
+                }
+                let parsed = if let Some(digits) = word.strip_prefix("0x") {
+                    u32::from_str_radix(digits, 16)
+                } else if let Some(digits) = word.strip_prefix("0b") {
+                    u32::from_str_radix(digits, 2)
+                } else {
+                    word.parse()
+                };
+                parsed
+                    .map(Term::Number)
+                    .map_err(|_| format!("invalid number `{}`", word))
+            }
+            Some('\'') => {
+                self.pos += 1;
+                let c = self.character('\'')?;
+                self.expect('\'')?;
+                Ok(Term::Number(c as u32))
+            }
+            _ => {
+                let name = self.name().ok_or_else(|| self.unexpected())?;
+                Ok(Term::Symbol(self.qualify(name)?))
+            }
+        }
+    }
+
+    /// Whether the cursor is at the `.` before the fractional part of a float, as opposed to a width
+    /// suffix
+    fn at_fraction(&self) -> bool {
+        let mut chars = self.rest().chars();
+        chars.next() == Some('.') && matches!(chars.next(), Some(c) if c.is_ascii_digit())
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        while !self.eat_raw('"') {
+            string.push(self.character('"')?);
+        }
+        Ok(string.into_bytes())
+    }
+
+    /// Reads a single, possibly escaped, character from a string or character literal
+    fn character(&mut self, quote: char) -> Result<char, String> {
+        let c = match self.peek() {
+            Some(c) if c != quote => c,
+            _ => return Err("unterminated literal".to_string()),
+        };
+        self.pos += c.len_utf8();
+        if c != '\\' {
+            return Ok(c);
+        }
+        let escaped = self
+            .peek()
+            .ok_or_else(|| "unterminated literal".to_string())?;
+        self.pos += escaped.len_utf8();
+        match escaped {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '0' => Ok('\0'),
+            '\\' | '\'' | '"' => Ok(escaped),
+            _ => Err(format!("unknown escape `\\{}`", escaped)),
+        }
+    }
+
+    /// Parses a symbol name, which may be local if it starts with a `.`
+    fn name(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let start = self.pos;
+        self.eat_raw('.');
+        match self.word() {
+            Some(_) => Some(&self.text[start..self.pos]),
+            None => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+
+    fn qualify(&self, name: &str) -> Result<String, String> {
+        if !name.starts_with('.') {
+            Ok(name.to_string())
+        } else if self.scope.is_empty() {
+            Err(format!(
+                "local name `{}` used before any global label",
+                name
+            ))
+        } else {
+            Ok(format!("{}{}", self.scope, name))
+        }
+    }
+
+    /// Reads a run of alphanumeric characters and underscores
+    fn word(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        let length = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or_else(|| self.rest().len());
+        self.pos += length;
+        if length == 0 {
+            None
+        } else {
+            Some(&self.text[start..self.pos])
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`", c))
+        }
+    }
+
+    /// Skips whitespace then consumes the character if it's next
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.eat_raw(c)
+    }
+
+    fn eat_raw(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.rest().trim().is_empty()
+    }
+
+    fn unexpected(&self) -> String {
+        match self.rest().trim().chars().next() {
+            Some(c) => format!("unexpected `{}`", c),
+            None => "unexpected end of line".to_string(),
+        }
+    }
+}
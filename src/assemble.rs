@@ -0,0 +1,34 @@
+//! Assembles a source file into a program image that can be loaded by the UI or the headless runner
+use rust_computer::asm;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: rust_computer_asm <source> [-o <output>]";
+
+pub fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (source_path, output_path) = match args.as_slice() {
+        [source] => (source, Path::new(source).with_extension("bin")),
+        [source, flag, output] if flag == "-o" => (source, Path::new(output).to_path_buf()),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let source = fs::read_to_string(source_path).unwrap_or_else(|err| {
+        eprintln!("Unable to read {}: {}", source_path, err);
+        process::exit(2);
+    });
+    let image = asm::assemble(&source).unwrap_or_else(|err| {
+        eprintln!("{}: {}", source_path, err);
+        process::exit(1);
+    });
+    fs::write(&output_path, &image).unwrap_or_else(|err| {
+        eprintln!("Unable to write {}: {}", output_path.display(), err);
+        process::exit(2);
+    });
+}
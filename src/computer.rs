@@ -1,20 +1,95 @@
-use crate::cpu::CpuResult;
+use crate::cpu::CpuPanic;
 use crate::cpu::{Cpu, Memory};
+use crate::devices::{
+    self, Audio, BlockDevice, Console, Dma, Framebuffer, Keyboard, Random, Rtc, Timer,
+};
+use std::fmt;
 
 pub struct Computer {
     pub memory: Memory,
     pub cpu: Cpu,
+    pub state: RunState,
+}
+
+/// Whether the computer is still executing instructions, and if not, why it stopped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunState {
+    Running,
+    /// The program executed a `halt` instruction with the given exit code
+    Halted(u32),
+    Faulted(CpuPanic),
 }
 
 impl Computer {
+    /// Creates a computer with the given amount of RAM and all the standard devices attached
     pub fn new(memory_size: usize) -> Computer {
+        let mut memory = Memory::new(memory_size);
+        memory.attach(devices::CONSOLE_ADDRESS, Console::new());
+        memory.attach_with_irq(devices::TIMER_ADDRESS, devices::TIMER_IRQ, Timer::new());
+        memory.attach(devices::FRAMEBUFFER_ADDRESS, Framebuffer::new());
+        memory.attach_with_irq(
+            devices::KEYBOARD_ADDRESS,
+            devices::KEYBOARD_IRQ,
+            Keyboard::new(),
+        );
+        memory.attach_with_irq(devices::DMA_ADDRESS, devices::DMA_IRQ, Dma::new());
+        memory.attach(devices::RTC_ADDRESS, Rtc::new());
+        memory.attach(devices::RANDOM_ADDRESS, Random::new());
+        memory.attach(devices::AUDIO_ADDRESS, Audio::new());
         Computer {
-            memory: Memory::new(memory_size),
+            memory,
             cpu: Cpu::new(),
+            state: RunState::Running,
         }
     }
 
-    pub fn tick(&mut self) {
-        self.cpu.tick(&mut self.memory);
+    /// Copies a program image into the start of RAM, truncating it if it's larger than RAM
+    pub fn load_image(&mut self, image: &[u8]) {
+        self.memory.ram_mut().load(image);
+    }
+
+    /// Makes the clock and random number generator depend only on their settings and the number of
+    /// ticks, so every run of a program behaves the same. The clock starts `start_time` seconds
+    /// after the epoch and the random number generator is seeded with `seed`.
+    pub fn set_deterministic(&mut self, seed: u64, start_time: u64) {
+        self.memory
+            .device_mut::<Rtc>()
+            .unwrap()
+            .set_deterministic(start_time);
+        self.memory.device_mut::<Random>().unwrap().reseed(seed);
+    }
+
+    /// Attaches a disk. Disks aren't standard since they need an image file on the host.
+    pub fn attach_disk(&mut self, disk: BlockDevice) {
+        self.memory
+            .attach_with_irq(devices::BLOCK_ADDRESS, devices::BLOCK_IRQ, disk);
+    }
+
+    /// Executes a single instruction and ticks every device, then returns the resulting state. Once
+    /// the computer has stopped running this does nothing.
+    pub fn tick(&mut self) -> RunState {
+        if self.state == RunState::Running {
+            let result = self.cpu.tick(&mut self.memory);
+            self.memory.tick(&mut self.cpu.interrupts);
+            match result {
+                Ok(()) => {
+                    if let Some(code) = self.cpu.halted {
+                        self.state = RunState::Halted(code);
+                    }
+                }
+                Err(panic) => self.state = RunState::Faulted(panic),
+            }
+        }
+        self.state
+    }
+}
+
+impl fmt::Display for RunState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunState::Running => write!(f, "Running"),
+            RunState::Halted(code) => write!(f, "Halted with exit code {}", code),
+            RunState::Faulted(panic) => write!(f, "Faulted: {}", panic),
+        }
     }
 }
@@ -1,11 +1,28 @@
 use super::*;
 use crate::cpu::CpuResult;
 use rust_computer_macros::bits;
-use std::num::Wrapping;
+use std::fmt;
+
+/// The maximum number of nested calls before the CPU panics
+pub const MAX_FRAMES: usize = 1024;
+/// The maximum number of vars a single frame can allocate
+pub const MAX_FRAME_VARS: u32 = 65536;
+/// The maximum number of vars all the frames on the stack can allocate between them, so deep
+/// recursion can't make the host allocate `MAX_FRAMES * MAX_FRAME_VARS` words
+pub const MAX_STACK_VARS: usize = 262_144;
 
 pub struct Cpu {
     pub frames: Vec<StackFrame>,
     pub program_counter: ProgramCounter,
+    /// the exit code passed to `halt`, once the program has halted
+    pub halted: Option<u32>,
+    pub flags: Flags,
+    pub interrupts: Interrupts,
+    /// the address `tick` jumps to when an instruction faults, if any
+    pub fault_handler: Option<u32>,
+    /// the original values of the frame vars written by the current instruction, so a fault can
+    /// undo them
+    var_undo: Vec<(usize, u32)>,
 }
 
 impl Cpu {
@@ -13,66 +30,433 @@ impl Cpu {
         Cpu {
             frames: vec![StackFrame::new(0)],
             program_counter: ProgramCounter::new(0),
+            halted: None,
+            flags: Flags::default(),
+            interrupts: Interrupts::new(),
+            fault_handler: None,
+            var_undo: Vec::new(),
         }
     }
 
+    /// Services a pending interrupt if there is one, then executes a single instruction. Faults are
+    /// passed to the fault handler if one is installed, and only returned if it can't take them.
     pub fn tick(&mut self, memory: &mut Memory) -> CpuResult<()> {
-        match memory.read_byte(self.program_counter.advance())? {
-            0b0000_0000 => {}
-            op if bits!(op; "0000_0001") => {
-                // move
-                let source = Location::decode(memory, &mut self.program_counter)?;
-                let dest = Location::decode(memory, &mut self.program_counter)?;
+        self.step(memory)
+            .or_else(|panic| self.enter_fault_handler(panic))
+    }
+
+    fn step(&mut self, memory: &mut Memory) -> CpuResult<()> {
+        let pc = self.program_counter.address;
+        self.service_interrupt(memory)
+            .map_err(|panic| panic.at(pc))?;
+        let pc = self.program_counter.address;
+        let registers = self.frame().map_err(|panic| panic.at(pc))?.registers;
+        let flags = self.flags;
+        self.var_undo.clear();
+        self.execute(memory).map_err(|panic| {
+            self.undo(registers, flags);
+            panic.at(pc)
+        })
+    }
+
+    /// Rolls the current frame back to how it was before a faulting instruction, so the registers a
+    /// `[rN]+` or `-[rN]` operand had already moved are where they started when the instruction is
+    /// retried. Memory and device side effects, such as reading a device register, aren't undone.
+    fn undo(&mut self, registers: [u32; 16], flags: Flags) {
+        self.flags = flags;
+        if let Some(frame) = self.frames.last_mut() {
+            frame.registers = registers;
+            for (index, value) in self.var_undo.drain(..).rev() {
+                frame.vars[index] = value;
+            }
+        }
+    }
+
+    /// Raises an interrupt line, to be serviced before the next instruction if it isn't masked
+    pub fn raise_interrupt(&mut self, irq: u32) -> CpuResult<()> {
+        self.interrupts.raise(irq)
+    }
+
+    /// Jumps to the handler of the next ready interrupt, saving the interrupted state in a new frame
+    fn service_interrupt(&mut self, memory: &Memory) -> CpuResult<()> {
+        let irq = match self.interrupts.ready() {
+            Some(irq) => irq,
+            None => return Ok(()),
+        };
+        let handler = memory.read_word(self.interrupts.vector(irq))?;
+        self.call(0, Address(handler))?;
+        self.frame_mut()?.interrupted = Some(SavedState {
+            flags: self.flags,
+            interrupts_enabled: true,
+            fault: false,
+        });
+        self.interrupts.enabled = false;
+        self.interrupts.acknowledge(irq);
+        Ok(())
+    }
+
+    /// Pushes a frame for the fault handler with the fault code in `r0`, the faulting PC in `r1` and
+    /// any extra detail about the fault in `r2`. Returning from the handler with `reti` retries the
+    /// faulting instruction, which `step` has already rolled back.
+    ///
+    /// A fault inside the handler itself, or with no handler installed, is returned unhandled.
+    fn enter_fault_handler(&mut self, panic: CpuPanic) -> CpuResult<()> {
+        let handler = match self.fault_handler {
+            Some(handler) => handler,
+            None => return Err(panic),
+        };
+        let in_handler = self
+            .frames
+            .iter()
+            .any(|frame| matches!(frame.interrupted, Some(saved) if saved.fault));
+        if in_handler {
+            return Err(panic);
+        }
+
+        // the handler always gets a frame, even if the fault was a stack overflow
+        let mut frame = StackFrame::new(0);
+        if let Some(faulted) = self.frames.last() {
+            frame.registers = faulted.registers;
+        }
+        frame.registers[0] = panic.kind.code();
+        frame.registers[1] = panic.pc;
+        frame.registers[2] = panic.kind.detail();
+        frame.return_address = panic.pc;
+        frame.interrupted = Some(SavedState {
+            flags: self.flags,
+            interrupts_enabled: self.interrupts.enabled,
+            fault: true,
+        });
+        self.frames.push(frame);
+        self.interrupts.enabled = false;
+        self.program_counter.address = handler;
+        Ok(())
+    }
+
+    fn execute(&mut self, memory: &mut Memory) -> CpuResult<()> {
+        match Instruction::decode(memory, &mut self.program_counter)? {
+            Instruction::Nop => {}
+            Instruction::Move { source, dest } => {
                 let value = self.get_value(memory, source)?;
                 self.set_value(memory, dest, value)?;
             }
-            op if bits!(op; "0000_1xxx") => {
-                // unsigned arithmetic
-                let a = Location::decode(memory, &mut self.program_counter)?;
-                let b = Location::decode(memory, &mut self.program_counter)?;
-                let dest = Location::decode(memory, &mut self.program_counter)?;
-                let value_a = Wrapping(self.get_value(memory, a)?);
-                let value_b = Wrapping(self.get_value(memory, b)?);
-
-                let result = match op & 0b111 {
-                    0b000 => value_a + value_b,
-                    0b001 => value_a - value_b,
-                    0b010 => value_a * value_b,
-                    0b011 => value_a / value_b,
-                    0b100 => value_a % value_b,
-                    _ => return Err(CpuPanic::new()),
-                };
-                self.set_value(memory, dest, result.0)?;
-            }
-            op if bits!(op; "0001_0000") => {
-                // unconditional jump
-                self.program_counter.address =
-                    memory.read_word(self.program_counter.advance_n(4))?;
-            }
-            op if bits!(op; "0001_0xxx") => {
-                // conditional jump
-                let a = Location::decode(memory, &mut self.program_counter)?;
-                let b = Location::decode(memory, &mut self.program_counter)?;
-                let dest = memory.read_word(self.program_counter.advance_n(4))?;
-                let value_a = self.get_value(memory, a)?;
-                let value_b = self.get_value(memory, b)?;
-
-                let result = match op & 0b111 {
-                    0b001 => value_a == value_b,
-                    0b010 => value_a != value_b,
-                    0b011 => value_a < value_b,
-                    0b100 => value_a <= value_b,
-                    0b101 => value_a >= value_b,
-                    0b110 => value_a > value_b,
-                    _ => return Err(CpuPanic::new()),
-                };
-                if result {
-                    self.program_counter.address = dest;
-                }
+            Instruction::Halt => self.halted = Some(0),
+            Instruction::HaltCode { code } => self.halted = Some(self.get_value(memory, code)?),
+
+            Instruction::Ei => self.interrupts.enabled = true,
+            Instruction::Di => self.interrupts.enabled = false,
+            Instruction::Ivt { table } => self.interrupts.table = self.get_value(memory, table)?,
+            Instruction::Imask { mask } => self.interrupts.mask = self.get_value(memory, mask)?,
+
+            Instruction::Add { a, b, dest } => {
+                self.arithmetic(memory, a, b, dest, |a, b| add_with_carry(a, b, false))?
+            }
+            Instruction::Sub { a, b, dest } => {
+                self.arithmetic(memory, a, b, dest, |a, b| sub_with_borrow(a, b, false))?
+            }
+            Instruction::Mul { a, b, dest } => self.arithmetic(memory, a, b, dest, |a, b| {
+                let (result, carry) = a.overflowing_mul(b);
+                (result, carry, (a as i32).overflowing_mul(b as i32).1)
+            })?,
+            Instruction::Div { a, b, dest } => self.divide(memory, a, b, dest, |a, b| a / b)?,
+            Instruction::Rem { a, b, dest } => self.divide(memory, a, b, dest, |a, b| a % b)?,
+            Instruction::Adc { a, b, dest } => {
+                let carry = self.flags.carry;
+                self.arithmetic(memory, a, b, dest, |a, b| add_with_carry(a, b, carry))?
+            }
+            Instruction::Sbb { a, b, dest } => {
+                let borrow = self.flags.carry;
+                self.arithmetic(memory, a, b, dest, |a, b| sub_with_borrow(a, b, borrow))?
+            }
+
+            Instruction::And { a, b, dest } => self.logic(memory, a, b, dest, |a, b| a & b)?,
+            Instruction::Or { a, b, dest } => self.logic(memory, a, b, dest, |a, b| a | b)?,
+            Instruction::Xor { a, b, dest } => self.logic(memory, a, b, dest, |a, b| a ^ b)?,
+            Instruction::Not { source, dest } => self.unary_logic(memory, source, dest, |a| !a)?,
+            // shifting by 32 or more shifts every bit out
+            Instruction::Shl { a, b, dest } => {
+                self.logic(memory, a, b, dest, |a, b| a.checked_shl(b).unwrap_or(0))?
+            }
+            Instruction::Shr { a, b, dest } => {
+                self.logic(memory, a, b, dest, |a, b| a.checked_shr(b).unwrap_or(0))?
+            }
+            Instruction::Sar { a, b, dest } => {
+                self.logic(memory, a, b, dest, |a, b| ((a as i32) >> b.min(31)) as u32)?
+            }
+            Instruction::Rol { a, b, dest } => self.logic(memory, a, b, dest, u32::rotate_left)?,
+            Instruction::Ror { a, b, dest } => self.logic(memory, a, b, dest, u32::rotate_right)?,
+
+            Instruction::Idiv { a, b, dest } => self.divide(memory, a, b, dest, |a, b| {
+                (a as i32).wrapping_div(b as i32) as u32
+            })?,
+            Instruction::Irem { a, b, dest } => self.divide(memory, a, b, dest, |a, b| {
+                (a as i32).wrapping_rem(b as i32) as u32
+            })?,
+            Instruction::Sextb { source, dest } => {
+                self.unary_logic(memory, source, dest, |a| DataWidth::Byte.sign_extend(a))?
+            }
+            Instruction::Sexts { source, dest } => {
+                self.unary_logic(memory, source, dest, |a| DataWidth::Short.sign_extend(a))?
+            }
+
+            Instruction::Fadd { a, b, dest } => {
+                self.binary(memory, a, b, dest, float(|a, b| a + b))?
+            }
+            Instruction::Fsub { a, b, dest } => {
+                self.binary(memory, a, b, dest, float(|a, b| a - b))?
+            }
+            Instruction::Fmul { a, b, dest } => {
+                self.binary(memory, a, b, dest, float(|a, b| a * b))?
+            }
+            Instruction::Fdiv { a, b, dest } => {
+                self.binary(memory, a, b, dest, float(|a, b| a / b))?
+            }
+            Instruction::Fsqrt { source, dest } => {
+                self.unary(memory, source, dest, |a| f32::from_bits(a).sqrt().to_bits())?
+            }
+            Instruction::Itof { source, dest } => {
+                self.unary(memory, source, dest, |a| (a as i32 as f32).to_bits())?
+            }
+            Instruction::Utof { source, dest } => {
+                self.unary(memory, source, dest, |a| (a as f32).to_bits())?
+            }
+            Instruction::Ftoi { source, dest } => {
+                self.unary(memory, source, dest, |a| f32::from_bits(a) as i32 as u32)?
+            }
+            Instruction::Ftou { source, dest } => {
+                self.unary(memory, source, dest, |a| f32::from_bits(a) as u32)?
+            }
+
+            Instruction::Jmp { target } => self.program_counter.address = target.0,
+            Instruction::Jeq { a, b, target } => self.branch(memory, a, b, target, u32::eq)?,
+            Instruction::Jne { a, b, target } => self.branch(memory, a, b, target, u32::ne)?,
+            Instruction::Jlt { a, b, target } => self.branch(memory, a, b, target, u32::lt)?,
+            Instruction::Jle { a, b, target } => self.branch(memory, a, b, target, u32::le)?,
+            Instruction::Jge { a, b, target } => self.branch(memory, a, b, target, u32::ge)?,
+            Instruction::Jgt { a, b, target } => self.branch(memory, a, b, target, u32::gt)?,
+            Instruction::Ijlt { a, b, target } => {
+                self.branch(memory, a, b, target, |a, b| (*a as i32) < (*b as i32))?
+            }
+            Instruction::Ijle { a, b, target } => {
+                self.branch(memory, a, b, target, |a, b| (*a as i32) <= (*b as i32))?
+            }
+            Instruction::Ijge { a, b, target } => {
+                self.branch(memory, a, b, target, |a, b| (*a as i32) >= (*b as i32))?
+            }
+            Instruction::Ijgt { a, b, target } => {
+                self.branch(memory, a, b, target, |a, b| (*a as i32) > (*b as i32))?
+            }
+            Instruction::Fjeq { a, b, target } => {
+                self.branch(memory, a, b, target, float_condition(f32::eq))?
+            }
+            Instruction::Fjne { a, b, target } => {
+                self.branch(memory, a, b, target, float_condition(f32::ne))?
+            }
+            Instruction::Fjlt { a, b, target } => {
+                self.branch(memory, a, b, target, float_condition(f32::lt))?
+            }
+            Instruction::Fjle { a, b, target } => {
+                self.branch(memory, a, b, target, float_condition(f32::le))?
+            }
+            Instruction::Fjge { a, b, target } => {
+                self.branch(memory, a, b, target, float_condition(f32::ge))?
+            }
+            Instruction::Fjgt { a, b, target } => {
+                self.branch(memory, a, b, target, float_condition(f32::gt))?
+            }
+            Instruction::Jc { target } => self.jump_if(self.flags.carry, target),
+            Instruction::Jnc { target } => self.jump_if(!self.flags.carry, target),
+            Instruction::Jo { target } => self.jump_if(self.flags.overflow, target),
+            Instruction::Jno { target } => self.jump_if(!self.flags.overflow, target),
+            Instruction::Jz { target } => self.jump_if(self.flags.zero, target),
+            Instruction::Jnz { target } => self.jump_if(!self.flags.zero, target),
+            Instruction::Jn { target } => self.jump_if(self.flags.negative, target),
+            Instruction::Jnn { target } => self.jump_if(!self.flags.negative, target),
+
+            Instruction::Call { target } => self.call(0, target)?,
+            Instruction::Callf { vars, target } => {
+                let vars = self.get_value(memory, vars)?;
+                self.call(vars, target)?
+            }
+            Instruction::Ret => self.ret()?,
+            Instruction::Reti => {
+                let frame = self.frame()?;
+                let target = frame.return_address;
+                self.reti(target)?
             }
-            _ => return Err(CpuPanic::new()),
+            Instruction::RetiTo { target } => {
+                let target = self.get_value(memory, target)?;
+                self.reti(target)?
+            }
+            Instruction::Int { irq } => {
+                let irq = self.get_value(memory, irq)?;
+                self.interrupts.raise(irq)?;
+            }
+            Instruction::Fhandler { handler } => {
+                let handler = self.get_value(memory, handler)?;
+                self.fault_handler = if handler == 0 { None } else { Some(handler) };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies an operation to the values of `a` and `b`, storing the result in `dest` and setting
+    /// the flags. `op` returns the result along with the carry and overflow flags.
+    fn arithmetic(
+        &mut self,
+        memory: &mut Memory,
+        a: Location,
+        b: Location,
+        dest: Location,
+        op: impl FnOnce(u32, u32) -> (u32, bool, bool),
+    ) -> CpuResult<()> {
+        let value_a = self.get_value(memory, a)?;
+        let value_b = self.get_value(memory, b)?;
+        let (result, carry, overflow) = op(value_a, value_b);
+        self.flags = Flags::new(result, carry, overflow);
+        self.set_value(memory, dest, result)
+    }
+
+    /// Like `arithmetic`, but for operations that can't carry or overflow
+    fn logic(
+        &mut self,
+        memory: &mut Memory,
+        a: Location,
+        b: Location,
+        dest: Location,
+        op: impl FnOnce(u32, u32) -> u32,
+    ) -> CpuResult<()> {
+        self.arithmetic(memory, a, b, dest, |a, b| (op(a, b), false, false))
+    }
+
+    /// Like `logic`, but faults instead of applying `op` if `b` is zero
+    fn divide(
+        &mut self,
+        memory: &mut Memory,
+        a: Location,
+        b: Location,
+        dest: Location,
+        op: impl FnOnce(u32, u32) -> u32,
+    ) -> CpuResult<()> {
+        let value_a = self.get_value(memory, a)?;
+        let value_b = self.get_value(memory, b)?;
+        if value_b == 0 {
+            return Err(CpuPanic::new(PanicKind::DivideByZero));
+        }
+        let result = op(value_a, value_b);
+        self.flags = Flags::new(result, false, false);
+        self.set_value(memory, dest, result)
+    }
+
+    /// Applies an operation to the values of `a` and `b`, storing the result in `dest` without
+    /// touching the flags
+    fn binary(
+        &mut self,
+        memory: &mut Memory,
+        a: Location,
+        b: Location,
+        dest: Location,
+        op: impl FnOnce(u32, u32) -> u32,
+    ) -> CpuResult<()> {
+        let value_a = self.get_value(memory, a)?;
+        let value_b = self.get_value(memory, b)?;
+        self.set_value(memory, dest, op(value_a, value_b))
+    }
+
+    /// Applies an operation to the value of `source`, storing the result in `dest`
+    fn unary(
+        &mut self,
+        memory: &mut Memory,
+        source: Location,
+        dest: Location,
+        op: impl FnOnce(u32) -> u32,
+    ) -> CpuResult<()> {
+        let value = self.get_value(memory, source)?;
+        self.set_value(memory, dest, op(value))
+    }
+
+    /// Like `unary`, but sets the flags the way `logic` does
+    fn unary_logic(
+        &mut self,
+        memory: &mut Memory,
+        source: Location,
+        dest: Location,
+        op: impl FnOnce(u32) -> u32,
+    ) -> CpuResult<()> {
+        let result = op(self.get_value(memory, source)?);
+        self.flags = Flags::new(result, false, false);
+        self.set_value(memory, dest, result)
+    }
+
+    /// Jumps to the target if the condition holds for the values of `a` and `b`
+    fn branch(
+        &mut self,
+        memory: &Memory,
+        a: Location,
+        b: Location,
+        target: Address,
+        condition: impl FnOnce(&u32, &u32) -> bool,
+    ) -> CpuResult<()> {
+        let value_a = self.get_value(memory, a)?;
+        let value_b = self.get_value(memory, b)?;
+        if condition(&value_a, &value_b) {
+            self.program_counter.address = target.0;
+        }
+        Ok(())
+    }
+
+    fn jump_if(&mut self, condition: bool, target: Address) {
+        if condition {
+            self.program_counter.address = target.0;
+        }
+    }
+
+    /// Pushes a frame with the given number of vars and jumps to the target. The new frame starts
+    /// with a copy of the caller's registers, so any of them can be used to pass arguments.
+    fn call(&mut self, var_count: u32, target: Address) -> CpuResult<()> {
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(CpuPanic::new(PanicKind::StackOverflow));
+        }
+        if var_count > MAX_FRAME_VARS {
+            return Err(CpuPanic::new(PanicKind::InvalidFrameVar(
+                var_count as usize,
+            )));
         }
+        let stack_vars: usize = self.frames.iter().map(|frame| frame.vars.len()).sum();
+        if stack_vars + var_count as usize > MAX_STACK_VARS {
+            return Err(CpuPanic::new(PanicKind::StackOverflow));
+        }
+        let mut frame = StackFrame::new(var_count);
+        frame.registers = self.frame()?.registers;
+        frame.return_address = self.program_counter.address;
+        self.frames.push(frame);
+        self.program_counter.address = target.0;
+        Ok(())
+    }
+
+    /// Pops the current frame and returns to the caller, passing `r0` back as the return value
+    fn ret(&mut self) -> CpuResult<()> {
+        if self.frames.len() < 2 {
+            return Err(CpuPanic::new(PanicKind::StackUnderflow));
+        }
+        let frame = self.frames.pop().unwrap();
+        self.frame_mut()?.registers[0] = frame.registers[0];
+        self.program_counter.address = frame.return_address;
+        Ok(())
+    }
 
+    /// Pops an interrupt or fault handler's frame, restores the interrupted state and continues at
+    /// the target
+    fn reti(&mut self, target: u32) -> CpuResult<()> {
+        let saved = self
+            .frame()?
+            .interrupted
+            .ok_or_else(|| CpuPanic::new(PanicKind::NotInInterrupt))?;
+        self.frames.pop();
+        self.flags = saved.flags;
+        self.interrupts.enabled = saved.interrupts_enabled;
+        self.program_counter.address = target;
         Ok(())
     }
 
@@ -86,11 +470,11 @@ impl Cpu {
             }
             Location::IndirectPostIncrement(direct, width) => {
                 let address = self.get_direct(direct)?;
-                self.set_direct(direct, address + width.size() as u32)?;
+                self.set_direct(direct, address.wrapping_add(width.size() as u32))?;
                 memory.read_width(width, address)
             }
             Location::IndirectPreDecrement(direct, width) => {
-                let address = self.get_direct(direct)? - width.size() as u32;
+                let address = self.get_direct(direct)?.wrapping_sub(width.size() as u32);
                 self.set_direct(direct, address)?;
                 memory.read_width(width, address)
             }
@@ -103,14 +487,14 @@ impl Cpu {
                 if index < 16 {
                     Ok(self.frame()?.registers[index])
                 } else {
-                    Err(CpuPanic::new())
+                    Err(CpuPanic::new(PanicKind::InvalidRegister(index)))
                 }
             }
             DirectAddress::Frame(index) => {
                 if index < self.frame()?.vars.len() {
                     Ok(self.frame()?.vars[index])
                 } else {
-                    Err(CpuPanic::new())
+                    Err(CpuPanic::new(PanicKind::InvalidFrameVar(index)))
                 }
             }
         }
@@ -118,7 +502,7 @@ impl Cpu {
 
     fn set_value(&mut self, memory: &mut Memory, location: Location, value: u32) -> CpuResult<()> {
         match location {
-            Location::Immediate(_) => Err(CpuPanic::new()),
+            Location::Immediate(_) => Err(CpuPanic::new(PanicKind::ImmediateWrite)),
             Location::Direct(direct) => self.set_direct(direct, value),
             Location::Indirect(direct, width) => {
                 let address = self.get_direct(direct)?;
@@ -126,11 +510,11 @@ impl Cpu {
             }
             Location::IndirectPostIncrement(direct, width) => {
                 let address = self.get_direct(direct)?;
-                self.set_direct(direct, address + width.size() as u32)?;
+                self.set_direct(direct, address.wrapping_add(width.size() as u32))?;
                 memory.write_width(width, address, value)
             }
             Location::IndirectPreDecrement(direct, width) => {
-                let address = self.get_direct(direct)? - width.size() as u32;
+                let address = self.get_direct(direct)?.wrapping_sub(width.size() as u32);
                 self.set_direct(direct, address)?;
                 memory.write_width(width, address, value)
             }
@@ -144,30 +528,67 @@ impl Cpu {
                     self.frame_mut()?.registers[index] = value;
                     Ok(())
                 } else {
-                    Err(CpuPanic::new())
+                    Err(CpuPanic::new(PanicKind::InvalidRegister(index)))
                 }
             }
             DirectAddress::Frame(index) => {
                 if index < self.frame()?.vars.len() {
-                    self.frame_mut()?.vars[index] = value;
+                    let frame = self.frame_mut()?;
+                    let original = std::mem::replace(&mut frame.vars[index], value);
+                    self.var_undo.push((index, original));
                     Ok(())
                 } else {
-                    Err(CpuPanic::new())
+                    Err(CpuPanic::new(PanicKind::InvalidFrameVar(index)))
                 }
             }
         }
     }
 
     pub fn frame(&self) -> Result<&StackFrame, CpuPanic> {
-        self.frames.last().ok_or_else(|| CpuPanic::new())
+        self.frames
+            .last()
+            .ok_or_else(|| CpuPanic::new(PanicKind::EmptyFrameStack))
     }
 
     pub fn frame_mut(&mut self) -> Result<&mut StackFrame, CpuPanic> {
-        self.frames.last_mut().ok_or_else(|| CpuPanic::new())
+        self.frames
+            .last_mut()
+            .ok_or_else(|| CpuPanic::new(PanicKind::EmptyFrameStack))
     }
 }
 
-enum Location {
+/// Adds with a carry in, returning the result, the carry out and whether the signed result
+/// overflowed
+fn add_with_carry(a: u32, b: u32, carry: bool) -> (u32, bool, bool) {
+    let wide = a as u64 + b as u64 + carry as u64;
+    let result = wide as u32;
+    // the signed result overflows if both operands have the same sign but the result doesn't
+    let overflow = !(a ^ b) & (a ^ result) & 0x8000_0000 != 0;
+    (result, wide > u32::MAX as u64, overflow)
+}
+
+/// Subtracts with a borrow in, returning the result, the borrow out and whether the signed result
+/// overflowed
+fn sub_with_borrow(a: u32, b: u32, borrow: bool) -> (u32, bool, bool) {
+    let result = a.wrapping_sub(b).wrapping_sub(borrow as u32);
+    // the signed result overflows if the operands have different signs and the result's sign
+    // doesn't match `a`
+    let overflow = (a ^ b) & (a ^ result) & 0x8000_0000 != 0;
+    (result, (b as u64 + borrow as u64) > a as u64, overflow)
+}
+
+/// Adapts a float operation to work on the bits of the operands
+fn float(op: impl FnOnce(f32, f32) -> f32) -> impl FnOnce(u32, u32) -> u32 {
+    move |a, b| op(f32::from_bits(a), f32::from_bits(b)).to_bits()
+}
+
+/// Adapts a float comparison to work on the bits of the operands
+fn float_condition(condition: impl FnOnce(&f32, &f32) -> bool) -> impl FnOnce(&u32, &u32) -> bool {
+    move |a, b| condition(&f32::from_bits(*a), &f32::from_bits(*b))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Location {
     Immediate(u32),
     Direct(DirectAddress),
     Indirect(DirectAddress, DataWidth),
@@ -175,8 +596,8 @@ enum Location {
     IndirectPreDecrement(DirectAddress, DataWidth),
 }
 
-#[derive(Clone, Copy)]
-enum DirectAddress {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DirectAddress {
     Register(usize),
     Frame(usize),
 }
@@ -213,7 +634,7 @@ impl Location {
                 let width = DataWidth::decode(it);
                 Location::IndirectPreDecrement(read_direct(memory, pc, it)?, width)
             }
-            _ => return Err(CpuPanic::new()),
+            it => return Err(CpuPanic::new(PanicKind::IllegalLocation(it))),
         });
 
         fn read_direct(
@@ -234,6 +655,29 @@ impl Location {
     }
 }
 
+/// Formats locations using the assembler's operand syntax
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Immediate(value) if *value < 0x100 => write!(f, "{}", value),
+            Location::Immediate(value) => write!(f, "0x{:x}", value),
+            Location::Direct(direct) => write!(f, "{}", direct),
+            Location::Indirect(direct, width) => write!(f, "[{}]{}", direct, width),
+            Location::IndirectPostIncrement(direct, width) => write!(f, "[{}]+{}", direct, width),
+            Location::IndirectPreDecrement(direct, width) => write!(f, "-[{}]{}", direct, width),
+        }
+    }
+}
+
+impl fmt::Display for DirectAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DirectAddress::Register(index) => write!(f, "r{}", index),
+            DirectAddress::Frame(index) => write!(f, "frame[{}]", index),
+        }
+    }
+}
+
 impl DataWidth {
     /// Gets the data width based on the last two bits in the passed byte
     pub fn decode(opcode: u8) -> DataWidth {
@@ -250,6 +694,19 @@ impl DataWidth {
 pub struct StackFrame {
     pub registers: [u32; 16],
     pub vars: Vec<u32>,
+    /// the address execution continues at when this frame returns
+    pub return_address: u32,
+    /// the state to restore if this frame was pushed by an interrupt or fault
+    pub interrupted: Option<SavedState>,
+}
+
+/// The state saved when entering an interrupt or fault handler, which `reti` restores
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SavedState {
+    pub flags: Flags,
+    pub interrupts_enabled: bool,
+    /// whether the frame belongs to the fault handler
+    pub fault: bool,
 }
 
 impl StackFrame {
@@ -257,17 +714,8 @@ impl StackFrame {
         StackFrame {
             registers: [0; 16],
             vars: vec![0; size as usize],
+            return_address: 0,
+            interrupted: None,
         }
     }
-
-    fn store(&mut self, var: u32, value: u32) {
-        *self
-            .vars
-            .get_mut(var as usize)
-            .expect("var is out of bounds") = value;
-    }
-
-    fn load(&self, var: u32) -> u32 {
-        *self.vars.get(var as usize).expect("var is out of bounds")
-    }
 }
@@ -1 +1,308 @@
+//! The instruction set. Each instruction is defined once in the `instruction_set!` invocation at the
+//! bottom of this file, which generates both the decoded `Instruction` enum and the `INSTRUCTIONS`
+//! table the assembler works from.
+use super::*;
+use std::fmt;
 
+/// The kinds of operands an instruction can take
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperandKind {
+    /// Any location, including immediates
+    Source,
+    /// Any location except immediates
+    Dest,
+    /// A raw 32-bit address
+    Address,
+}
+
+/// The raw 32-bit address operand of jump instructions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Address(pub u32);
+
+/// An entry in the `INSTRUCTIONS` table. Mnemonics aren't necessarily unique, but the combination
+/// of mnemonic and operand count is.
+pub struct InstructionInfo {
+    pub mnemonic: &'static str,
+    pub opcode: u8,
+    pub operands: &'static [OperandKind],
+}
+
+/// A value that can be decoded from the bytes following an opcode
+pub trait Operand: Sized + fmt::Display {
+    fn decode(memory: &Memory, pc: &mut ProgramCounter) -> CpuResult<Self>;
+
+    /// Whether the assembler can produce this operand. Some operands decode but can't be written in
+    /// assembly, like registers past `r15`.
+    fn assemblable(&self) -> bool {
+        true
+    }
+}
+
+impl Operand for Location {
+    fn decode(memory: &Memory, pc: &mut ProgramCounter) -> CpuResult<Location> {
+        Location::decode(memory, pc)
+    }
+
+    fn assemblable(&self) -> bool {
+        match self {
+            Location::Immediate(_) => true,
+            Location::Direct(direct)
+            | Location::Indirect(direct, _)
+            | Location::IndirectPostIncrement(direct, _)
+            | Location::IndirectPreDecrement(direct, _) => match direct {
+                DirectAddress::Register(index) => *index < 16,
+                DirectAddress::Frame(_) => true,
+            },
+        }
+    }
+}
+
+impl Operand for Address {
+    fn decode(memory: &Memory, pc: &mut ProgramCounter) -> CpuResult<Address> {
+        Ok(Address(memory.read_word(pc.advance_n(4))?))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:08x}", self.0)
+    }
+}
+
+macro_rules! instruction_set {
+    (@type Source) => { Location };
+    (@type Dest) => { Location };
+    (@type Address) => { Address };
+
+    ($(
+        $(#[$meta:meta])*
+        $name:ident = $opcode:literal, $mnemonic:literal $({ $($field:ident: $kind:ident),* })?;
+    )*) => {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub enum Instruction {
+            $(
+                $(#[$meta])*
+                $name $({ $($field: instruction_set!(@type $kind)),* })?,
+            )*
+        }
+
+        pub const INSTRUCTIONS: &[InstructionInfo] = &[
+            $(
+                InstructionInfo {
+                    mnemonic: $mnemonic,
+                    opcode: $opcode,
+                    operands: &[$($(OperandKind::$kind),*)?],
+                },
+            )*
+        ];
+
+        impl Instruction {
+            pub fn decode(memory: &Memory, pc: &mut ProgramCounter) -> CpuResult<Instruction> {
+                Ok(match memory.read_byte(pc.advance())? {
+                    $(
+                        $opcode => Instruction::$name $({ $($field: Operand::decode(memory, pc)?),* })?,
+                    )*
+                    opcode => return Err(CpuPanic::new(PanicKind::IllegalOpcode(opcode))),
+                })
+            }
+
+            /// Whether the assembler can produce this instruction, which isn't true of everything
+            /// that decodes
+            pub fn assemblable(&self) -> bool {
+                match self {
+                    $(
+                        Instruction::$name $({ $($field),* })? => {
+                            true $($(&& $field.assemblable())*)?
+                        }
+                    )*
+                }
+            }
+        }
+
+        /// Formats the instruction using the assembler's syntax
+        impl fmt::Display for Instruction {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let (mnemonic, operands): (&str, &[&dyn fmt::Display]) = match self {
+                    $(
+                        Instruction::$name $({ $($field),* })? => {
+                            ($mnemonic, &[$($($field),*)?])
+                        }
+                    )*
+                };
+                write!(f, "{}", mnemonic)?;
+                for (i, operand) in operands.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+instruction_set! {
+    Nop = 0b0000_0000, "nop";
+    /// `dest = source`
+    Move = 0b0000_0001, "move" { source: Source, dest: Dest };
+    /// Stops the computer with an exit code of zero
+    Halt = 0b0000_0010, "halt";
+    /// Stops the computer with the given exit code
+    HaltCode = 0b0000_0011, "halt" { code: Source };
+
+    // interrupts. Servicing an interrupt pushes a frame like `call` does, saves the flags and
+    // disables interrupts until the handler returns with `reti`.
+    /// Enables interrupts
+    Ei = 0b0000_0100, "ei";
+    /// Disables interrupts. Interrupts raised in the meantime stay pending.
+    Di = 0b0000_0101, "di";
+    /// Sets the address of the interrupt vector table
+    Ivt = 0b0000_0110, "ivt" { table: Source };
+    /// Sets the interrupt mask. Only lines whose bit is set can interrupt the CPU.
+    Imask = 0b0000_0111, "imask" { mask: Source };
+
+    // unsigned arithmetic. Integer arithmetic and bitwise instructions set the zero and negative
+    // flags based on their result. Only addition, subtraction and multiplication set the carry and
+    // overflow flags, the rest clear them.
+    /// `dest = a + b`
+    Add = 0b0000_1000, "add" { a: Source, b: Source, dest: Dest };
+    /// `dest = a - b`
+    Sub = 0b0000_1001, "sub" { a: Source, b: Source, dest: Dest };
+    /// `dest = a * b`
+    Mul = 0b0000_1010, "mul" { a: Source, b: Source, dest: Dest };
+    /// `dest = a / b`
+    Div = 0b0000_1011, "div" { a: Source, b: Source, dest: Dest };
+    /// `dest = a % b`
+    Rem = 0b0000_1100, "rem" { a: Source, b: Source, dest: Dest };
+    /// `dest = a + b + carry`
+    Adc = 0b0000_1101, "adc" { a: Source, b: Source, dest: Dest };
+    /// `dest = a - b - carry`
+    Sbb = 0b0000_1110, "sbb" { a: Source, b: Source, dest: Dest };
+
+    // bitwise
+    /// `dest = a & b`
+    And = 0b0010_0000, "and" { a: Source, b: Source, dest: Dest };
+    /// `dest = a | b`
+    Or = 0b0010_0001, "or" { a: Source, b: Source, dest: Dest };
+    /// `dest = a ^ b`
+    Xor = 0b0010_0010, "xor" { a: Source, b: Source, dest: Dest };
+    /// `dest = !source`
+    Not = 0b0010_0011, "not" { source: Source, dest: Dest };
+    /// `dest = a << b`
+    Shl = 0b0010_0100, "shl" { a: Source, b: Source, dest: Dest };
+    /// `dest = a >> b`, filling with zeros
+    Shr = 0b0010_0101, "shr" { a: Source, b: Source, dest: Dest };
+    /// `dest = a >> b`, filling with copies of the sign bit
+    Sar = 0b0010_0110, "sar" { a: Source, b: Source, dest: Dest };
+    /// Rotates `a` left by `b` bits
+    Rol = 0b0010_0111, "rol" { a: Source, b: Source, dest: Dest };
+    /// Rotates `a` right by `b` bits
+    Ror = 0b0010_1000, "ror" { a: Source, b: Source, dest: Dest };
+
+    // signed arithmetic. Addition, subtraction, multiplication and equality are the same for
+    // signed and unsigned values, and `sar` is the signed right shift.
+    /// `dest = a / b`, rounding towards zero
+    Idiv = 0b0011_0000, "idiv" { a: Source, b: Source, dest: Dest };
+    /// `dest = a % b`, with the sign of `a`
+    Irem = 0b0011_0001, "irem" { a: Source, b: Source, dest: Dest };
+    /// Sign extends the lowest byte of `source`
+    Sextb = 0b0011_0010, "sextb" { source: Source, dest: Dest };
+    /// Sign extends the lowest short of `source`
+    Sexts = 0b0011_0011, "sexts" { source: Source, dest: Dest };
+
+    // floating point. Floats are stored as the bits of an IEEE-754 single-precision value.
+    /// `dest = a + b`
+    Fadd = 0b0100_0000, "fadd" { a: Source, b: Source, dest: Dest };
+    /// `dest = a - b`
+    Fsub = 0b0100_0001, "fsub" { a: Source, b: Source, dest: Dest };
+    /// `dest = a * b`
+    Fmul = 0b0100_0010, "fmul" { a: Source, b: Source, dest: Dest };
+    /// `dest = a / b`
+    Fdiv = 0b0100_0011, "fdiv" { a: Source, b: Source, dest: Dest };
+    /// `dest = sqrt(source)`
+    Fsqrt = 0b0100_0100, "fsqrt" { source: Source, dest: Dest };
+    /// Converts a signed integer to a float
+    Itof = 0b0100_1000, "itof" { source: Source, dest: Dest };
+    /// Converts an unsigned integer to a float
+    Utof = 0b0100_1001, "utof" { source: Source, dest: Dest };
+    /// Converts a float to a signed integer, rounding towards zero and saturating at the limits
+    Ftoi = 0b0100_1010, "ftoi" { source: Source, dest: Dest };
+    /// Converts a float to an unsigned integer, rounding towards zero and saturating at the limits
+    Ftou = 0b0100_1011, "ftou" { source: Source, dest: Dest };
+
+    // jumps
+    Jmp = 0b0001_0000, "jmp" { target: Address };
+    /// Jumps if `a == b`
+    Jeq = 0b0001_0001, "jeq" { a: Source, b: Source, target: Address };
+    /// Jumps if `a != b`
+    Jne = 0b0001_0010, "jne" { a: Source, b: Source, target: Address };
+    /// Jumps if `a < b`
+    Jlt = 0b0001_0011, "jlt" { a: Source, b: Source, target: Address };
+    /// Jumps if `a <= b`
+    Jle = 0b0001_0100, "jle" { a: Source, b: Source, target: Address };
+    /// Jumps if `a >= b`
+    Jge = 0b0001_0101, "jge" { a: Source, b: Source, target: Address };
+    /// Jumps if `a > b`
+    Jgt = 0b0001_0110, "jgt" { a: Source, b: Source, target: Address };
+
+    // signed jumps
+    /// Jumps if `a < b`
+    Ijlt = 0b0011_1000, "ijlt" { a: Source, b: Source, target: Address };
+    /// Jumps if `a <= b`
+    Ijle = 0b0011_1001, "ijle" { a: Source, b: Source, target: Address };
+    /// Jumps if `a >= b`
+    Ijge = 0b0011_1010, "ijge" { a: Source, b: Source, target: Address };
+    /// Jumps if `a > b`
+    Ijgt = 0b0011_1011, "ijgt" { a: Source, b: Source, target: Address };
+
+    // float jumps. Every comparison with NaN is false, except `fjne` which is always true.
+    /// Jumps if `a == b`
+    Fjeq = 0b0101_0000, "fjeq" { a: Source, b: Source, target: Address };
+    /// Jumps if `a != b`
+    Fjne = 0b0101_0001, "fjne" { a: Source, b: Source, target: Address };
+    /// Jumps if `a < b`
+    Fjlt = 0b0101_0010, "fjlt" { a: Source, b: Source, target: Address };
+    /// Jumps if `a <= b`
+    Fjle = 0b0101_0011, "fjle" { a: Source, b: Source, target: Address };
+    /// Jumps if `a >= b`
+    Fjge = 0b0101_0100, "fjge" { a: Source, b: Source, target: Address };
+    /// Jumps if `a > b`
+    Fjgt = 0b0101_0101, "fjgt" { a: Source, b: Source, target: Address };
+
+    // flag jumps
+    /// Jumps if the carry flag is set
+    Jc = 0b0101_1000, "jc" { target: Address };
+    /// Jumps if the carry flag is clear
+    Jnc = 0b0101_1001, "jnc" { target: Address };
+    /// Jumps if the overflow flag is set
+    Jo = 0b0101_1010, "jo" { target: Address };
+    /// Jumps if the overflow flag is clear
+    Jno = 0b0101_1011, "jno" { target: Address };
+    /// Jumps if the zero flag is set
+    Jz = 0b0101_1100, "jz" { target: Address };
+    /// Jumps if the zero flag is clear
+    Jnz = 0b0101_1101, "jnz" { target: Address };
+    /// Jumps if the negative flag is set
+    Jn = 0b0101_1110, "jn" { target: Address };
+    /// Jumps if the negative flag is clear
+    Jnn = 0b0101_1111, "jnn" { target: Address };
+
+    // subroutines. Each call pushes a new stack frame whose registers start as a copy of the
+    // caller's, so arguments can be passed in any register. Returning copies the callee's `r0` into
+    // the caller's `r0`.
+    /// Calls a subroutine with no frame vars
+    Call = 0b0001_1000, "call" { target: Address };
+    /// Calls a subroutine with `vars` zeroed frame vars
+    Callf = 0b0001_1001, "callf" { vars: Source, target: Address };
+    /// Returns from the current subroutine
+    Ret = 0b0001_1010, "ret";
+    /// Returns from an interrupt or fault handler, restoring the flags and whether interrupts were
+    /// enabled
+    Reti = 0b0001_1011, "reti";
+    /// Raises an interrupt line
+    Int = 0b0001_1100, "int" { irq: Source };
+    /// Returns from an interrupt or fault handler like `reti`, but continues at `target` instead of
+    /// the interrupted instruction
+    RetiTo = 0b0001_1101, "reti" { target: Source };
+    /// Installs a fault handler, or removes it if `handler` is zero. Faults then call the handler
+    /// with the fault code in `r0`, the faulting PC in `r1` and any detail in `r2`.
+    Fhandler = 0b0001_1110, "fhandler" { handler: Source };
+}
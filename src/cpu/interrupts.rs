@@ -0,0 +1,66 @@
+use super::*;
+
+/// The number of interrupt lines. Each one has an entry in the vector table.
+pub const INTERRUPT_COUNT: u32 = 32;
+
+/// The interrupt controller. Interrupts are raised by devices, the host or the `int` instruction,
+/// and stay pending until the CPU services them between instructions.
+///
+/// The vector table is `INTERRUPT_COUNT` words in memory, each holding the address of the handler
+/// for that line.
+pub struct Interrupts {
+    /// whether pending interrupts are serviced at all. Cleared while a handler is running.
+    pub enabled: bool,
+    /// the lines that are allowed to interrupt the CPU, one bit per line
+    pub mask: u32,
+    /// the lines that have been raised but not yet serviced, one bit per line
+    pub pending: u32,
+    /// the address of the vector table
+    pub table: u32,
+}
+
+impl Interrupts {
+    pub fn new() -> Interrupts {
+        Interrupts {
+            enabled: false,
+            mask: !0,
+            pending: 0,
+            table: 0,
+        }
+    }
+
+    /// Marks an interrupt line as pending
+    pub fn raise(&mut self, irq: u32) -> CpuResult<()> {
+        if irq >= INTERRUPT_COUNT {
+            return Err(CpuPanic::new(PanicKind::InvalidInterrupt(irq)));
+        }
+        self.pending |= 1 << irq;
+        Ok(())
+    }
+
+    /// The lowest pending interrupt that is ready to be serviced, if any. It stays pending until
+    /// it's acknowledged.
+    pub fn ready(&self) -> Option<u32> {
+        let ready = self.pending & self.mask;
+        if !self.enabled || ready == 0 {
+            return None;
+        }
+        Some(ready.trailing_zeros())
+    }
+
+    /// Clears a pending interrupt once its handler has been entered
+    pub fn acknowledge(&mut self, irq: u32) {
+        self.pending &= !(1 << irq);
+    }
+
+    /// The address of the vector table entry for an interrupt line
+    pub fn vector(&self, irq: u32) -> u32 {
+        self.table.wrapping_add(irq * 4)
+    }
+}
+
+impl Default for Interrupts {
+    fn default() -> Interrupts {
+        Interrupts::new()
+    }
+}
@@ -1,94 +1,242 @@
 use super::*;
-use std::convert::TryInto;
+use std::any::Any;
+use std::fmt;
 
+/// A device attached to the memory bus. Offsets are relative to the start of the range the device
+/// is mapped at, and the bus never passes an access that extends past `size`.
+pub trait Device: Send + Sync {
+    /// The number of bytes of address space the device claims
+    fn size(&self) -> u32;
+
+    /// Reads a value. Reads shouldn't have side effects, since the UI and disassembler also read
+    /// memory, so devices trigger actions through writes instead.
+    fn read(&self, offset: u32, width: DataWidth) -> u32;
+
+    fn write(&mut self, offset: u32, width: DataWidth, value: u32);
+
+    /// Advances the device by one emulated tick, returning whether it's requesting an interrupt.
+    /// Devices can access the rest of the bus through `memory` to transfer data directly, though
+    /// their own range is unmapped for the duration of the call.
+    fn tick(&mut self, _memory: &mut Memory) -> bool {
+        false
+    }
+
+    /// Used by `Memory::device` to get back the concrete device type
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// The memory bus, which routes each access to the device mapped at that address
 pub struct Memory {
-    pub data: Vec<u8>,
+    mappings: Vec<Mapping>,
+}
+
+struct Mapping {
+    start: u32,
+    device: Box<dyn Device>,
+    /// the interrupt line raised when the device requests an interrupt
+    irq: Option<u32>,
 }
 
 impl Memory {
+    /// Creates a bus with `memory_size` bytes of RAM mapped at address zero
     pub fn new(memory_size: usize) -> Memory {
-        Memory {
-            data: vec![0; memory_size],
-        }
+        let mut memory = Memory {
+            mappings: Vec::new(),
+        };
+        memory.attach(0, Ram::new(memory_size));
+        memory
     }
 
-    pub fn read_byte(&self, address: u32) -> CpuResult<u8> {
-        let address = address as usize;
-        if address >= self.data.len() {
-            return Err(CpuPanic::new());
-        }
-        Ok(self.data[address])
+    /// Maps a device at the given address.
+    ///
+    /// Panics if the device overlaps one that's already attached.
+    pub fn attach(&mut self, start: u32, device: impl Device + 'static) {
+        self.attach_mapping(start, Box::new(device), None);
     }
 
-    pub fn read_short(&self, address: u32) -> CpuResult<u16> {
-        let address = address as usize;
-        if address >= self.data.len() - 2 {
-            return Err(CpuPanic::new());
+    /// Maps a device at the given address, connecting its interrupt requests to an interrupt line.
+    ///
+    /// Panics if the device overlaps one that's already attached or the line doesn't exist.
+    pub fn attach_with_irq(&mut self, start: u32, irq: u32, device: impl Device + 'static) {
+        assert!(irq < INTERRUPT_COUNT, "invalid interrupt line {}", irq);
+        self.attach_mapping(start, Box::new(device), Some(irq));
+    }
+
+    fn attach_mapping(&mut self, start: u32, device: Box<dyn Device>, irq: Option<u32>) {
+        let end = start as u64 + device.size() as u64;
+        for mapping in &self.mappings {
+            let mapping_end = mapping.start as u64 + mapping.device.size() as u64;
+            if (start as u64) < mapping_end && (mapping.start as u64) < end {
+                panic!(
+                    "device at 0x{:08x} overlaps the device at 0x{:08x}",
+                    start, mapping.start
+                );
+            }
         }
-        Ok(u16::from_be_bytes(
-            self.data[address..address + 2].try_into().unwrap(),
-        ))
+        self.mappings.push(Mapping { start, device, irq });
     }
 
-    pub fn read_word(&self, address: u32) -> CpuResult<u32> {
-        let address = address as usize;
-        if address >= self.data.len() - 4 {
-            return Err(CpuPanic::new());
+    /// Ticks every device, raising the interrupts they request
+    pub fn tick(&mut self, interrupts: &mut Interrupts) {
+        for index in 0..self.mappings.len() {
+            // take the device out of the bus so it can be given access to the rest of it
+            let mut device =
+                std::mem::replace(&mut self.mappings[index].device, Box::new(Unmapped));
+            let interrupt = device.tick(self);
+            let mapping = &mut self.mappings[index];
+            mapping.device = device;
+            if let (true, Some(irq)) = (interrupt, mapping.irq) {
+                interrupts.pending |= 1 << irq;
+            }
         }
+    }
 
-        Ok(u32::from_be_bytes(
-            self.data[address..address + 4].try_into().unwrap(),
-        ))
+    /// Finds the first attached device of the given type
+    pub fn device<T: Device + 'static>(&self) -> Option<&T> {
+        self.mappings
+            .iter()
+            .find_map(|mapping| mapping.device.as_any().downcast_ref())
     }
 
-    pub fn write_byte(&mut self, address: u32, value: u8) -> CpuResult<()> {
-        let address = address as usize;
-        if address >= self.data.len() {
-            return Err(CpuPanic::new());
-        }
+    pub fn device_mut<T: Device + 'static>(&mut self) -> Option<&mut T> {
+        self.mappings
+            .iter_mut()
+            .find_map(|mapping| mapping.device.as_any_mut().downcast_mut())
+    }
 
-        self.data[address as usize] = value;
-        Ok(())
+    /// The RAM mapped at address zero
+    pub fn ram(&self) -> &Ram {
+        self.device().expect("no RAM attached")
     }
 
-    pub fn write_short(&mut self, address: u32, value: u16) -> CpuResult<()> {
-        let address = address as usize;
-        if address >= self.data.len() - 2 {
-            return Err(CpuPanic::new());
+    pub fn ram_mut(&mut self) -> &mut Ram {
+        self.device_mut().expect("no RAM attached")
+    }
+
+    /// Finds the mapping that contains the whole access, returning its index and the offset of the
+    /// address into the device
+    fn find(&self, address: u32, width: DataWidth) -> CpuResult<(usize, u32)> {
+        for (index, mapping) in self.mappings.iter().enumerate() {
+            let offset = address.wrapping_sub(mapping.start);
+            if address >= mapping.start
+                && offset as u64 + width.size() as u64 <= mapping.device.size() as u64
+            {
+                return Ok((index, offset));
+            }
         }
+        Err(CpuPanic::new(PanicKind::OutOfBounds { address, width }))
+    }
 
-        self.data[address..address + 2].copy_from_slice(&value.to_be_bytes());
-        Ok(())
+    pub fn read_byte(&self, address: u32) -> CpuResult<u8> {
+        self.read_width(DataWidth::Byte, address).map(|x| x as u8)
     }
 
-    pub fn write_word(&mut self, address: u32, value: u32) -> CpuResult<()> {
-        let address = address as usize;
-        if address >= self.data.len() - 4 {
-            return Err(CpuPanic::new());
-        }
+    pub fn read_short(&self, address: u32) -> CpuResult<u16> {
+        self.read_width(DataWidth::Short, address).map(|x| x as u16)
+    }
 
-        self.data[address..address + 4].copy_from_slice(&value.to_be_bytes());
-        Ok(())
+    pub fn read_word(&self, address: u32) -> CpuResult<u32> {
+        self.read_width(DataWidth::Word, address)
+    }
+
+    pub fn write_byte(&mut self, address: u32, value: u8) -> CpuResult<()> {
+        self.write_width(DataWidth::Byte, address, value as u32)
+    }
+
+    pub fn write_short(&mut self, address: u32, value: u16) -> CpuResult<()> {
+        self.write_width(DataWidth::Short, address, value as u32)
+    }
+
+    pub fn write_word(&mut self, address: u32, value: u32) -> CpuResult<()> {
+        self.write_width(DataWidth::Word, address, value)
     }
 
     pub fn write_width(&mut self, width: DataWidth, address: u32, value: u32) -> CpuResult<()> {
-        match width {
-            DataWidth::Byte => self.write_byte(address, value as u8),
-            DataWidth::Short => self.write_short(address, value as u16),
-            DataWidth::Word => self.write_word(address, value),
-        }
+        let (index, offset) = self.find(address, width)?;
+        self.mappings[index]
+            .device
+            .write(offset, width, value & width.bitmask());
+        Ok(())
     }
 
     pub fn read_width(&self, width: DataWidth, address: u32) -> CpuResult<u32> {
-        match width {
-            DataWidth::Byte => self.read_byte(address).map(|x| x as u32),
-            DataWidth::Short => self.read_short(address).map(|x| x as u32),
-            DataWidth::Word => self.read_word(address),
+        let (index, offset) = self.find(address, width)?;
+        Ok(self.mappings[index].device.read(offset, width) & width.bitmask())
+    }
+}
+
+/// Stands in for a device while it's being ticked. It has no size, so nothing ever reaches it.
+struct Unmapped;
+
+impl Device for Unmapped {
+    fn size(&self) -> u32 {
+        0
+    }
+
+    fn read(&self, _offset: u32, _width: DataWidth) -> u32 {
+        0
+    }
+
+    fn write(&mut self, _offset: u32, _width: DataWidth, _value: u32) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Plain read/write memory, stored big-endian
+pub struct Ram {
+    pub data: Vec<u8>,
+}
+
+impl Ram {
+    pub fn new(size: usize) -> Ram {
+        Ram {
+            data: vec![0; size],
         }
     }
+
+    /// Copies an image into the start of RAM, truncating it if it's larger than RAM
+    pub fn load(&mut self, image: &[u8]) {
+        let limit = std::cmp::min(self.data.len(), image.len());
+        self.data[..limit].copy_from_slice(&image[..limit]);
+    }
 }
 
-#[derive(Clone, Copy)]
+impl Device for Ram {
+    fn size(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    fn read(&self, offset: u32, width: DataWidth) -> u32 {
+        let start = offset as usize;
+        self.data[start..start + width.size()]
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as u32)
+    }
+
+    fn write(&mut self, offset: u32, width: DataWidth, value: u32) {
+        let start = offset as usize;
+        let bytes = value.to_be_bytes();
+        self.data[start..start + width.size()].copy_from_slice(&bytes[4 - width.size()..]);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DataWidth {
     Byte,
     Short,
@@ -104,6 +252,15 @@ impl DataWidth {
         }
     }
 
+    /// Extends the sign bit of a value of this width to fill all 32 bits
+    pub fn sign_extend(&self, value: u32) -> u32 {
+        match self {
+            DataWidth::Byte => value as u8 as i8 as i32 as u32,
+            DataWidth::Short => value as u16 as i16 as i32 as u32,
+            DataWidth::Word => value,
+        }
+    }
+
     pub fn size(&self) -> usize {
         match self {
             DataWidth::Byte => 1,
@@ -112,3 +269,14 @@ impl DataWidth {
         }
     }
 }
+
+/// Formats the width as an assembler suffix, e.g. `.w`
+impl fmt::Display for DataWidth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataWidth::Byte => write!(f, ".b"),
+            DataWidth::Short => write!(f, ".s"),
+            DataWidth::Word => write!(f, ".w"),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod cpu;
 pub mod instructions;
+pub mod interrupts;
 pub mod memory;
 pub mod utils;
 
@@ -7,5 +8,6 @@ pub type CpuResult<T> = Result<T, CpuPanic>;
 
 pub use cpu::*;
 pub use instructions::*;
+pub use interrupts::*;
 pub use memory::*;
 pub use utils::*;
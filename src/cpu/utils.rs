@@ -1,4 +1,7 @@
 ///! Various utilities or small types that only serve to clutter the more focused cpu files
+use super::DataWidth;
+use std::error::Error;
+use std::fmt;
 
 pub struct ProgramCounter {
     pub address: u32,
@@ -12,22 +15,171 @@ impl ProgramCounter {
     /// Increments the address and returns the original value
     pub fn advance(&mut self) -> u32 {
         let original = self.address;
-        self.address += 1;
+        self.address = self.address.wrapping_add(1);
         return original;
     }
 
     /// Increments the address by n and returns the original value
     pub fn advance_n(&mut self, amount: usize) -> u32 {
         let original = self.address;
-        self.address += amount as u32;
+        self.address = self.address.wrapping_add(amount as u32);
         return original;
     }
 }
 
-pub struct CpuPanic {}
+/// The status flags set by arithmetic and logic instructions
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Flags {
+    /// The unsigned result didn't fit in 32 bits. For subtraction this is set when a borrow occurs.
+    pub carry: bool,
+    /// The signed result didn't fit in 32 bits
+    pub overflow: bool,
+    pub zero: bool,
+    /// The highest bit of the result was set
+    pub negative: bool,
+}
+
+impl Flags {
+    pub fn new(result: u32, carry: bool, overflow: bool) -> Flags {
+        Flags {
+            carry,
+            overflow,
+            zero: result == 0,
+            negative: result & 0x8000_0000 != 0,
+        }
+    }
+}
+
+/// Formats the flags as `CVZN`, with cleared flags replaced by `-`
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flag = |set: bool, name: char| if set { name } else { '-' };
+        write!(
+            f,
+            "{}{}{}{}",
+            flag(self.carry, 'C'),
+            flag(self.overflow, 'V'),
+            flag(self.zero, 'Z'),
+            flag(self.negative, 'N')
+        )
+    }
+}
+
+/// An unrecoverable error raised while executing an instruction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CpuPanic {
+    /// The address of the instruction that caused the panic
+    pub pc: u32,
+    pub kind: PanicKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PanicKind {
+    /// The opcode byte doesn't match any instruction
+    IllegalOpcode(u8),
+    /// The location byte doesn't match any addressing mode
+    IllegalLocation(u8),
+    /// A memory access extended past the end of memory
+    OutOfBounds {
+        address: u32,
+        width: DataWidth,
+    },
+    /// A register index was 16 or higher
+    InvalidRegister(usize),
+    /// A frame var index was past the end of the current frame
+    InvalidFrameVar(usize),
+    /// An immediate value was used as a destination
+    ImmediateWrite,
+    /// There was no stack frame to read from or write to
+    EmptyFrameStack,
+    /// A call was made with `MAX_FRAMES` frames already on the stack, or would take the stack past
+    /// `MAX_STACK_VARS` vars
+    StackOverflow,
+    /// A return was made from the outermost frame
+    StackUnderflow,
+    DivideByZero,
+    /// An interrupt line was `INTERRUPT_COUNT` or higher
+    InvalidInterrupt(u32),
+    /// A `reti` was executed outside of an interrupt handler
+    NotInInterrupt,
+}
 
 impl CpuPanic {
-    pub fn new() -> CpuPanic {
-        CpuPanic {}
+    /// Creates a panic with a program counter of zero. The CPU fills in the actual address once the
+    /// panic propagates up to `Cpu::tick`
+    pub fn new(kind: PanicKind) -> CpuPanic {
+        CpuPanic { pc: 0, kind }
+    }
+
+    pub fn at(self, pc: u32) -> CpuPanic {
+        CpuPanic { pc, ..self }
     }
 }
+
+impl PanicKind {
+    /// The fault code passed to the fault handler in `r0`
+    pub fn code(&self) -> u32 {
+        match self {
+            PanicKind::IllegalOpcode(_) => 1,
+            PanicKind::IllegalLocation(_) => 2,
+            PanicKind::OutOfBounds { .. } => 3,
+            PanicKind::InvalidRegister(_) => 4,
+            PanicKind::InvalidFrameVar(_) => 5,
+            PanicKind::ImmediateWrite => 6,
+            PanicKind::EmptyFrameStack => 7,
+            PanicKind::StackOverflow => 8,
+            PanicKind::StackUnderflow => 9,
+            PanicKind::DivideByZero => 10,
+            PanicKind::InvalidInterrupt(_) => 11,
+            PanicKind::NotInInterrupt => 12,
+        }
+    }
+
+    /// The extra detail passed to the fault handler in `r2`, such as the illegal opcode or the
+    /// address of an out of bounds access. Zero for faults with no detail.
+    pub fn detail(&self) -> u32 {
+        match self {
+            PanicKind::IllegalOpcode(opcode) => *opcode as u32,
+            PanicKind::IllegalLocation(location) => *location as u32,
+            PanicKind::OutOfBounds { address, .. } => *address,
+            PanicKind::InvalidRegister(index) => *index as u32,
+            PanicKind::InvalidFrameVar(index) => *index as u32,
+            PanicKind::InvalidInterrupt(irq) => *irq,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for CpuPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at 0x{:08x}", self.kind, self.pc)
+    }
+}
+
+impl fmt::Display for PanicKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PanicKind::IllegalOpcode(opcode) => write!(f, "illegal opcode 0x{:02x}", opcode),
+            PanicKind::IllegalLocation(location) => {
+                write!(f, "illegal location byte 0x{:02x}", location)
+            }
+            PanicKind::OutOfBounds { address, width } => write!(
+                f,
+                "out of bounds {}-byte access at 0x{:08x}",
+                width.size(),
+                address
+            ),
+            PanicKind::InvalidRegister(index) => write!(f, "invalid register r{}", index),
+            PanicKind::InvalidFrameVar(index) => write!(f, "invalid frame var {}", index),
+            PanicKind::ImmediateWrite => write!(f, "write to immediate value"),
+            PanicKind::EmptyFrameStack => write!(f, "empty frame stack"),
+            PanicKind::StackOverflow => write!(f, "stack overflow"),
+            PanicKind::StackUnderflow => write!(f, "return from the outermost frame"),
+            PanicKind::DivideByZero => write!(f, "divide by zero"),
+            PanicKind::InvalidInterrupt(irq) => write!(f, "invalid interrupt {}", irq),
+            PanicKind::NotInInterrupt => write!(f, "return from interrupt outside of a handler"),
+        }
+    }
+}
+
+impl Error for CpuPanic {}
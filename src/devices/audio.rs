@@ -0,0 +1,167 @@
+use super::clock::TICKS_PER_SECOND;
+use crate::cpu::{DataWidth, Device, Memory};
+use std::any::Any;
+use std::io::{self, Write};
+
+/// The tone's frequency in hertz, or zero to silence the channel
+const FREQUENCY: u32 = 0x0;
+/// The channel's volume, from 0 to 255
+const VOLUME: u32 = 0x4;
+/// One of the `WAVE_*` values. Unknown values play a square wave.
+const WAVEFORM: u32 = 0x8;
+/// The space each channel's registers take up. Channel `n`'s registers start at `n * CHANNEL_SIZE`.
+const CHANNEL_SIZE: u32 = 0x10;
+
+const WAVE_SQUARE: u32 = 0;
+const WAVE_TRIANGLE: u32 = 1;
+
+pub const CHANNELS: usize = 4;
+pub const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Clone, Copy)]
+struct Channel {
+    frequency: u32,
+    volume: u32,
+    waveform: u32,
+    /// how far through the current period the channel is, as a fraction of 2^32
+    phase: u32,
+}
+
+impl Channel {
+    const SILENT: Channel = Channel {
+        frequency: 0,
+        volume: 0,
+        waveform: WAVE_SQUARE,
+        phase: 0,
+    };
+
+    /// The channel's next sample, from -1 to 1
+    fn sample(&mut self) -> f64 {
+        if self.frequency == 0 {
+            return 0.;
+        }
+        let position = self.phase as f64 / (1u64 << 32) as f64;
+        let wave = match self.waveform {
+            WAVE_TRIANGLE => 1. - 4. * (position - 0.5).abs(),
+            _ => {
+                if position < 0.5 {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+        };
+        let step = ((self.frequency as u64) << 32) / SAMPLE_RATE as u64;
+        self.phase = self.phase.wrapping_add(step as u32);
+        wave * self.volume.min(255) as f64 / 255.
+    }
+}
+
+/// A tone generator with a few square or triangle wave channels. Samples are generated at
+/// `SAMPLE_RATE` in emulated time, where each tick is `1 / TICKS_PER_SECOND` seconds, but only
+/// while recording.
+pub struct Audio {
+    channels: [Channel; CHANNELS],
+    recording: bool,
+    samples: Vec<i16>,
+    /// counts up by `SAMPLE_RATE` each tick, producing a sample every `TICKS_PER_SECOND`
+    accumulator: u64,
+}
+
+impl Audio {
+    pub fn new() -> Audio {
+        Audio {
+            channels: [Channel::SILENT; CHANNELS],
+            recording: false,
+            samples: Vec::new(),
+            accumulator: 0,
+        }
+    }
+
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+    }
+
+    /// Takes the samples recorded since the last call
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        std::mem::take(&mut self.samples)
+    }
+
+    fn mix(&mut self) -> i16 {
+        let total: f64 = self.channels.iter_mut().map(Channel::sample).sum();
+        (total / CHANNELS as f64 * i16::MAX as f64) as i16
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Audio {
+        Audio::new()
+    }
+}
+
+impl Device for Audio {
+    fn size(&self) -> u32 {
+        CHANNEL_SIZE * CHANNELS as u32
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        let channel = &self.channels[(offset / CHANNEL_SIZE) as usize];
+        match offset % CHANNEL_SIZE {
+            FREQUENCY => channel.frequency,
+            VOLUME => channel.volume,
+            WAVEFORM => channel.waveform,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, value: u32) {
+        let channel = &mut self.channels[(offset / CHANNEL_SIZE) as usize];
+        match offset % CHANNEL_SIZE {
+            FREQUENCY => channel.frequency = value,
+            VOLUME => channel.volume = value,
+            WAVEFORM => channel.waveform = value,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, _memory: &mut Memory) -> bool {
+        self.accumulator += SAMPLE_RATE as u64;
+        while self.accumulator >= TICKS_PER_SECOND {
+            self.accumulator -= TICKS_PER_SECOND;
+            if self.recording {
+                let sample = self.mix();
+                self.samples.push(sample);
+            }
+        }
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Writes samples as a mono 16-bit WAV file
+pub fn write_wav(writer: &mut impl Write, samples: &[i16]) -> io::Result<()> {
+    let data_size = samples.len() as u32 * 2;
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?; // format chunk size
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // channels
+    writer.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    writer.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?; // bytes per second
+    writer.write_all(&2u16.to_le_bytes())?; // bytes per frame
+    writer.write_all(&16u16.to_le_bytes())?; // bits per sample
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use crate::cpu::{CpuResult, DataWidth, Device, Memory};
+use std::any::Any;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// The first sector the next command transfers
+const SECTOR: u32 = 0x00;
+/// The memory address the next command transfers to or from
+const ADDRESS: u32 = 0x04;
+/// The number of sectors the next command transfers
+const COUNT: u32 = 0x08;
+/// Writing one of the `COMMAND_*` values starts a transfer on the next tick. The other registers
+/// shouldn't be changed until it completes.
+const COMMAND: u32 = 0x0c;
+/// Reads one of the `STATUS_*` values
+const STATUS: u32 = 0x10;
+/// Reads the number of sectors on the disk
+const SECTOR_COUNT: u32 = 0x14;
+/// A combination of the `CONTROL_*` bits
+const CONTROL: u32 = 0x18;
+
+/// Copies sectors from the disk into memory
+const COMMAND_READ: u32 = 1;
+/// Copies memory onto sectors of the disk
+const COMMAND_WRITE: u32 = 2;
+
+const STATUS_READY: u32 = 0;
+const STATUS_BUSY: u32 = 1;
+/// The last command used an unknown command value, sectors past the end of the disk, memory that
+/// doesn't exist, or the host failed to access the disk image
+const STATUS_ERROR: u32 = 2;
+
+/// Requests an interrupt when a command completes
+const CONTROL_INTERRUPT: u32 = 0b1;
+
+pub const SECTOR_SIZE: usize = 512;
+/// The number of sectors transferred each tick, so a large transfer doesn't stall the emulator on
+/// host I/O for a single instruction
+pub const SECTORS_PER_TICK: u32 = 4;
+
+/// A disk backed by an image file on the host. Programs set up a transfer with the `SECTOR`,
+/// `ADDRESS` and `COUNT` registers, then write a command, and the device copies the data directly
+/// to or from memory.
+pub struct BlockDevice {
+    file: File,
+    sector_count: u32,
+    sector: u32,
+    address: u32,
+    count: u32,
+    /// the command in progress, if any
+    command: Option<u32>,
+    /// the number of sectors the command in progress has transferred so far
+    transferred: u32,
+    status: u32,
+    control: u32,
+}
+
+impl BlockDevice {
+    /// Opens a disk image, which must be a whole number of sectors long
+    pub fn open(path: impl AsRef<Path>) -> io::Result<BlockDevice> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let length = file.metadata()?.len();
+        if length % SECTOR_SIZE as u64 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("disk image isn't a multiple of {} bytes", SECTOR_SIZE),
+            ));
+        }
+        Ok(BlockDevice {
+            file,
+            sector_count: (length / SECTOR_SIZE as u64) as u32,
+            sector: 0,
+            address: 0,
+            count: 0,
+            command: None,
+            transferred: 0,
+            status: STATUS_READY,
+            control: 0,
+        })
+    }
+
+    /// Transfers the next few sectors of a command, returning whether they succeeded
+    fn run(&mut self, command: u32, memory: &mut Memory) -> bool {
+        let end = self.sector as u64 + self.count as u64;
+        if end > self.sector_count as u64 {
+            return false;
+        }
+        let mut buffer = [0; SECTOR_SIZE];
+        let last = self
+            .count
+            .min(self.transferred.saturating_add(SECTORS_PER_TICK));
+        for i in self.transferred..last {
+            let position = (self.sector + i) as u64 * SECTOR_SIZE as u64;
+            let address = self
+                .address
+                .wrapping_add(i.wrapping_mul(SECTOR_SIZE as u32));
+            let succeeded = match command {
+                COMMAND_READ => {
+                    self.read_sector(position, &mut buffer).is_ok()
+                        && copy_to_memory(memory, address, &buffer).is_ok()
+                }
+                COMMAND_WRITE => {
+                    copy_from_memory(memory, address, &mut buffer).is_ok()
+                        && self.write_sector(position, &buffer).is_ok()
+                }
+                _ => false,
+            };
+            if !succeeded {
+                return false;
+            }
+            self.transferred = i + 1;
+        }
+        true
+    }
+
+    fn read_sector(&mut self, position: u64, buffer: &mut [u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(position))?;
+        self.file.read_exact(buffer)
+    }
+
+    fn write_sector(&mut self, position: u64, buffer: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(position))?;
+        self.file.write_all(buffer)
+    }
+}
+
+fn copy_to_memory(memory: &mut Memory, address: u32, buffer: &[u8]) -> CpuResult<()> {
+    for (i, &byte) in buffer.iter().enumerate() {
+        memory.write_byte(address.wrapping_add(i as u32), byte)?;
+    }
+    Ok(())
+}
+
+fn copy_from_memory(memory: &Memory, address: u32, buffer: &mut [u8]) -> CpuResult<()> {
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        *byte = memory.read_byte(address.wrapping_add(i as u32))?;
+    }
+    Ok(())
+}
+
+impl Device for BlockDevice {
+    fn size(&self) -> u32 {
+        0x1c
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        match offset {
+            SECTOR => self.sector,
+            ADDRESS => self.address,
+            COUNT => self.count,
+            STATUS => self.status,
+            SECTOR_COUNT => self.sector_count,
+            CONTROL => self.control,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, value: u32) {
+        match offset {
+            SECTOR => self.sector = value,
+            ADDRESS => self.address = value,
+            COUNT => self.count = value,
+            COMMAND => {
+                self.command = Some(value);
+                self.transferred = 0;
+                self.status = STATUS_BUSY;
+            }
+            CONTROL => self.control = value,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, memory: &mut Memory) -> bool {
+        let command = match self.command {
+            Some(command) => command,
+            None => return false,
+        };
+        if !self.run(command, memory) {
+            self.status = STATUS_ERROR;
+        } else if self.transferred == self.count {
+            self.status = STATUS_READY;
+        } else {
+            return false;
+        }
+        self.command = None;
+        self.control & CONTROL_INTERRUPT != 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
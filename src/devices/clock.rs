@@ -0,0 +1,97 @@
+use crate::cpu::{DataWidth, Device, Memory};
+use std::any::Any;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writing any value latches the current time into the registers below, so they can be read
+/// without the time changing in between
+const LATCH: u32 = 0x0;
+/// The latched time as whole seconds since the Unix epoch
+const SECONDS_LOW: u32 = 0x4;
+const SECONDS_HIGH: u32 = 0x8;
+/// The milliseconds past the latched second
+const MILLIS: u32 = 0xc;
+
+/// How fast time passes in deterministic mode
+pub const TICKS_PER_SECOND: u64 = 1_000_000;
+
+/// A real-time clock. It normally reports the host's time, but in deterministic mode it starts at
+/// a fixed time and advances with the tick counter, so runs are reproducible.
+pub struct Rtc {
+    /// the time deterministic mode started at, in seconds since the epoch, or `None` to use the
+    /// host's time
+    start: Option<u64>,
+    ticks: u64,
+    /// the latched time, in milliseconds since the epoch
+    latched: u64,
+}
+
+impl Rtc {
+    pub fn new() -> Rtc {
+        Rtc {
+            start: None,
+            ticks: 0,
+            latched: 0,
+        }
+    }
+
+    /// Switches to deterministic mode, starting `start` seconds after the epoch. Times too large to
+    /// count in milliseconds saturate.
+    pub fn set_deterministic(&mut self, start: u64) {
+        self.start = Some(start);
+        self.ticks = 0;
+    }
+
+    /// The current time in milliseconds since the epoch
+    fn now(&self) -> u64 {
+        match self.start {
+            Some(start) => start
+                .saturating_mul(1000)
+                .saturating_add(self.ticks / (TICKS_PER_SECOND / 1000)),
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_millis() as u64)
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl Default for Rtc {
+    fn default() -> Rtc {
+        Rtc::new()
+    }
+}
+
+impl Device for Rtc {
+    fn size(&self) -> u32 {
+        0x10
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        let seconds = self.latched / 1000;
+        match offset {
+            SECONDS_LOW => seconds as u32,
+            SECONDS_HIGH => (seconds >> 32) as u32,
+            MILLIS => (self.latched % 1000) as u32,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, _value: u32) {
+        if offset == LATCH {
+            self.latched = self.now();
+        }
+    }
+
+    fn tick(&mut self, _memory: &mut Memory) -> bool {
+        self.ticks += 1;
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
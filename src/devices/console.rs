@@ -0,0 +1,74 @@
+use crate::cpu::{DataWidth, Device};
+use std::any::Any;
+use std::collections::VecDeque;
+
+/// Writing a byte here sends it to the host
+const OUTPUT: u32 = 0x0;
+/// Reads the next byte of input, or zero if there isn't any. Writing any value discards it.
+const INPUT: u32 = 0x4;
+/// Reads the number of bytes waiting in the input queue
+const INPUT_COUNT: u32 = 0x8;
+
+/// A serial console. Bytes the program writes are buffered until the host takes them with
+/// `take_output`, and bytes the host sends with `push_input` wait in a queue until the program
+/// reads them.
+pub struct Console {
+    output: Vec<u8>,
+    input: VecDeque<u8>,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            output: Vec::new(),
+            input: VecDeque::new(),
+        }
+    }
+
+    /// Takes everything the program has written since the last call
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+    }
+}
+
+impl Default for Console {
+    fn default() -> Console {
+        Console::new()
+    }
+}
+
+impl Device for Console {
+    fn size(&self) -> u32 {
+        12
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        match offset {
+            INPUT => self.input.front().copied().unwrap_or(0) as u32,
+            INPUT_COUNT => self.input.len() as u32,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, value: u32) {
+        match offset {
+            OUTPUT => self.output.push(value as u8),
+            INPUT => {
+                self.input.pop_front();
+            }
+            _ => {}
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
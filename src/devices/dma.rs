@@ -0,0 +1,164 @@
+use crate::cpu::{DataWidth, Device, Memory};
+use std::any::Any;
+
+/// The address bytes are copied from. These three registers advance as the transfer progresses.
+const SOURCE: u32 = 0x00;
+/// The address bytes are copied or filled to
+const DEST: u32 = 0x04;
+/// The number of bytes left to transfer
+const LENGTH: u32 = 0x08;
+/// The byte written by fills
+const VALUE: u32 = 0x0c;
+/// The number of bytes transferred each tick. Zero is treated as one, and anything above
+/// `MAX_RATE` as `MAX_RATE`.
+const RATE: u32 = 0x10;
+/// Writing one of the `COMMAND_*` values starts a transfer on the next tick
+const COMMAND: u32 = 0x14;
+/// Reads one of the `STATUS_*` values
+const STATUS: u32 = 0x18;
+/// A combination of the `CONTROL_*` bits
+const CONTROL: u32 = 0x1c;
+
+const COMMAND_COPY: u32 = 1;
+const COMMAND_FILL: u32 = 2;
+
+const STATUS_READY: u32 = 0;
+const STATUS_BUSY: u32 = 1;
+/// The last command was unknown or accessed memory that doesn't exist
+const STATUS_ERROR: u32 = 2;
+
+/// The most bytes transferred in a single tick, so one transfer can't stall the host
+pub const MAX_RATE: u32 = 4096;
+
+/// Requests an interrupt when a transfer finishes
+const CONTROL_INTERRUPT: u32 = 0b001;
+/// Keeps reading the same source address. Reading a device register through the bus doesn't
+/// pop it, so this repeats one byte rather than draining a device's input FIFO.
+const CONTROL_FIXED_SOURCE: u32 = 0b010;
+/// Keeps writing the same destination address, e.g. to feed a device's output register
+const CONTROL_FIXED_DEST: u32 = 0b100;
+
+/// A DMA engine that copies or fills ranges of the bus a few bytes per tick, so bulk transfers
+/// don't need an instruction per word. It can feed a device's output register, but transfers out
+/// of a device FIFO such as the keyboard's aren't supported.
+pub struct Dma {
+    source: u32,
+    dest: u32,
+    length: u32,
+    value: u32,
+    rate: u32,
+    /// the running command, if any
+    command: Option<u32>,
+    status: u32,
+    control: u32,
+}
+
+impl Dma {
+    pub fn new() -> Dma {
+        Dma {
+            source: 0,
+            dest: 0,
+            length: 0,
+            value: 0,
+            rate: 4,
+            command: None,
+            status: STATUS_READY,
+            control: 0,
+        }
+    }
+
+    /// Transfers a single byte, returning whether it succeeded
+    fn transfer_byte(&mut self, command: u32, memory: &mut Memory) -> bool {
+        let byte = match command {
+            COMMAND_COPY => match memory.read_byte(self.source) {
+                Ok(byte) => byte,
+                Err(_) => return false,
+            },
+            COMMAND_FILL => self.value as u8,
+            _ => return false,
+        };
+        if memory.write_byte(self.dest, byte).is_err() {
+            return false;
+        }
+        if command == COMMAND_COPY && self.control & CONTROL_FIXED_SOURCE == 0 {
+            self.source = self.source.wrapping_add(1);
+        }
+        if self.control & CONTROL_FIXED_DEST == 0 {
+            self.dest = self.dest.wrapping_add(1);
+        }
+        self.length -= 1;
+        true
+    }
+}
+
+impl Default for Dma {
+    fn default() -> Dma {
+        Dma::new()
+    }
+}
+
+impl Device for Dma {
+    fn size(&self) -> u32 {
+        0x20
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        match offset {
+            SOURCE => self.source,
+            DEST => self.dest,
+            LENGTH => self.length,
+            VALUE => self.value,
+            RATE => self.rate,
+            STATUS => self.status,
+            CONTROL => self.control,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, value: u32) {
+        match offset {
+            SOURCE => self.source = value,
+            DEST => self.dest = value,
+            LENGTH => self.length = value,
+            VALUE => self.value = value,
+            RATE => self.rate = value.min(MAX_RATE),
+            COMMAND => {
+                self.command = Some(value);
+                self.status = STATUS_BUSY;
+            }
+            CONTROL => self.control = value,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, memory: &mut Memory) -> bool {
+        let command = match self.command {
+            Some(command) => command,
+            None => return false,
+        };
+        for _ in 0..self.rate.max(1) {
+            if self.length == 0 {
+                break;
+            }
+            if !self.transfer_byte(command, memory) {
+                self.status = STATUS_ERROR;
+                self.command = None;
+                return self.control & CONTROL_INTERRUPT != 0;
+            }
+        }
+        if self.length != 0 {
+            return false;
+        }
+        self.status = STATUS_READY;
+        self.command = None;
+        self.control & CONTROL_INTERRUPT != 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,111 @@
+use crate::cpu::{DataWidth, Device};
+use std::any::Any;
+
+/// One byte per pixel, row by row
+const PIXELS: u32 = 0x0000;
+/// 256 palette entries, each a word in the form `0x00RRGGBB`
+const PALETTE: u32 = 0xc000;
+/// One of the `MODE_*` values
+const MODE: u32 = 0xc400;
+
+/// Each pixel is an index into the palette
+const MODE_PALETTE: u32 = 0;
+/// Each pixel is a color in the form `RRRGGGBB`
+const MODE_RGB332: u32 = 1;
+
+/// A 256x192 display with one byte per pixel, which is either looked up in the palette or used
+/// directly as a color depending on the mode.
+pub struct Framebuffer {
+    /// the pixels followed by the palette, stored big-endian like RAM
+    data: Vec<u8>,
+    mode: u32,
+}
+
+impl Framebuffer {
+    pub const WIDTH: usize = 256;
+    pub const HEIGHT: usize = 192;
+
+    /// Creates a blank framebuffer in palette mode. The palette starts out matching the RGB332
+    /// colors, so both modes look the same until the program changes it.
+    pub fn new() -> Framebuffer {
+        let mut framebuffer = Framebuffer {
+            data: vec![0; MODE as usize],
+            mode: MODE_PALETTE,
+        };
+        for index in 0..256 {
+            let offset = PALETTE + index * 4;
+            framebuffer.write(offset, DataWidth::Word, rgb332(index as u8));
+        }
+        framebuffer
+    }
+
+    /// Renders the display as 8-bit RGB triples, row by row
+    pub fn to_rgb(&self) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(Self::WIDTH * Self::HEIGHT * 3);
+        let pixels = &self.data[PIXELS as usize..PALETTE as usize];
+        for &pixel in pixels {
+            let color = match self.mode {
+                MODE_RGB332 => rgb332(pixel),
+                _ => self.read(PALETTE + pixel as u32 * 4, DataWidth::Word),
+            };
+            rgb.extend_from_slice(&color.to_be_bytes()[1..]);
+        }
+        rgb
+    }
+}
+
+/// Expands an `RRRGGGBB` color to `0x00RRGGBB`
+fn rgb332(color: u8) -> u32 {
+    let red = (color >> 5) as u32 * 255 / 7;
+    let green = (color >> 2 & 0b111) as u32 * 255 / 7;
+    let blue = (color & 0b11) as u32 * 255 / 3;
+    red << 16 | green << 8 | blue
+}
+
+impl Default for Framebuffer {
+    fn default() -> Framebuffer {
+        Framebuffer::new()
+    }
+}
+
+impl Device for Framebuffer {
+    fn size(&self) -> u32 {
+        MODE + 4
+    }
+
+    fn read(&self, offset: u32, width: DataWidth) -> u32 {
+        if offset >= MODE {
+            return if offset == MODE { self.mode } else { 0 };
+        }
+        let start = offset as usize;
+        // an access that straddles the end of the palette only sees the palette's bytes
+        let end = (start + width.size()).min(self.data.len());
+        self.data[start..end]
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as u32)
+    }
+
+    fn write(&mut self, offset: u32, width: DataWidth, value: u32) {
+        if offset >= MODE {
+            if offset == MODE {
+                self.mode = value;
+            }
+            return;
+        }
+        let start = offset as usize;
+        let bytes = value.to_be_bytes();
+        for (i, &byte) in bytes[4 - width.size()..].iter().enumerate() {
+            if let Some(target) = self.data.get_mut(start + i) {
+                *target = byte;
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,110 @@
+use crate::cpu::{DataWidth, Device, Memory};
+use std::any::Any;
+use std::collections::VecDeque;
+
+/// Reads the oldest queued event, or zero if there isn't one. Writing any value discards it.
+const EVENT: u32 = 0x0;
+/// Reads the number of queued events
+const COUNT: u32 = 0x4;
+/// A combination of the `CONTROL_*` bits
+const CONTROL: u32 = 0x8;
+
+/// Requests an interrupt whenever an event is queued
+const CONTROL_INTERRUPT: u32 = 0b1;
+
+/// Events past this many are dropped until the program catches up
+const CAPACITY: usize = 64;
+
+/// Set in events for released keys
+pub const RELEASED: u32 = 1 << 31;
+
+/// Scancodes for keys that don't have an obvious ASCII code. Letters and digits use their
+/// uppercase ASCII code, and space, backspace, tab, enter (`\n`), escape and delete use their
+/// ASCII control codes.
+pub const KEY_UP: u8 = 0x80;
+pub const KEY_DOWN: u8 = 0x81;
+pub const KEY_LEFT: u8 = 0x82;
+pub const KEY_RIGHT: u8 = 0x83;
+pub const KEY_SHIFT: u8 = 0x84;
+pub const KEY_CONTROL: u8 = 0x85;
+pub const KEY_ALT: u8 = 0x86;
+
+/// A keyboard that queues key presses and releases for the program to read. Each event is a word
+/// made up of the `RELEASED` bit, the scancode in bits 16-23 and the character the key typed (or
+/// zero) in bits 0-15.
+pub struct Keyboard {
+    events: VecDeque<u32>,
+    control: u32,
+    /// whether an event was queued since the last tick
+    queued: bool,
+}
+
+impl Keyboard {
+    pub fn new() -> Keyboard {
+        Keyboard {
+            events: VecDeque::new(),
+            control: 0,
+            queued: false,
+        }
+    }
+
+    /// Queues a key event. Characters outside the basic multilingual plane are sent as zero.
+    pub fn push(&mut self, scancode: u8, character: Option<char>, released: bool) {
+        if self.events.len() >= CAPACITY {
+            return;
+        }
+        let character = character
+            .map(|c| c as u32)
+            .filter(|&c| c <= 0xffff)
+            .unwrap_or(0);
+        let released = if released { RELEASED } else { 0 };
+        self.events
+            .push_back(released | (scancode as u32) << 16 | character);
+        self.queued = true;
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Keyboard {
+        Keyboard::new()
+    }
+}
+
+impl Device for Keyboard {
+    fn size(&self) -> u32 {
+        12
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        match offset {
+            EVENT => self.events.front().copied().unwrap_or(0),
+            COUNT => self.events.len() as u32,
+            CONTROL => self.control,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, value: u32) {
+        match offset {
+            EVENT => {
+                self.events.pop_front();
+            }
+            CONTROL => self.control = value,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, _memory: &mut Memory) -> bool {
+        let queued = self.queued;
+        self.queued = false;
+        queued && self.control & CONTROL_INTERRUPT != 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,51 @@
+//! Memory-mapped devices. `Computer::new` attaches each of them to the bus at the addresses below,
+//! which sit at the top of the address space well clear of RAM.
+pub mod audio;
+pub mod block;
+pub mod clock;
+mod console;
+pub mod dma;
+mod framebuffer;
+pub mod keyboard;
+mod random;
+mod timer;
+
+pub use audio::Audio;
+pub use block::BlockDevice;
+pub use clock::Rtc;
+pub use console::Console;
+pub use dma::Dma;
+pub use framebuffer::Framebuffer;
+pub use keyboard::Keyboard;
+pub use random::Random;
+pub use timer::Timer;
+
+/// The address of the console's registers
+pub const CONSOLE_ADDRESS: u32 = 0xffff_0000;
+/// The address of the timer's registers
+pub const TIMER_ADDRESS: u32 = 0xffff_0100;
+/// The address of the framebuffer's pixels, palette and mode register
+pub const FRAMEBUFFER_ADDRESS: u32 = 0xfff0_0000;
+/// The most RAM a computer can have before it runs into the framebuffer, the lowest device
+pub const MAX_RAM_SIZE: usize = FRAMEBUFFER_ADDRESS as usize;
+/// The address of the keyboard's registers
+pub const KEYBOARD_ADDRESS: u32 = 0xffff_0200;
+/// The address of the disk's registers, if one is attached with `Computer::attach_disk`
+pub const BLOCK_ADDRESS: u32 = 0xffff_0300;
+/// The address of the DMA controller's registers
+pub const DMA_ADDRESS: u32 = 0xffff_0400;
+/// The address of the real-time clock's registers
+pub const RTC_ADDRESS: u32 = 0xffff_0500;
+/// The address of the random number generator's registers
+pub const RANDOM_ADDRESS: u32 = 0xffff_0600;
+/// The address of the tone generator's channel registers
+pub const AUDIO_ADDRESS: u32 = 0xffff_0700;
+
+/// The interrupt line raised by the timer
+pub const TIMER_IRQ: u32 = 0;
+/// The interrupt line raised by the keyboard
+pub const KEYBOARD_IRQ: u32 = 1;
+/// The interrupt line raised by the disk
+pub const BLOCK_IRQ: u32 = 2;
+/// The interrupt line raised by the DMA controller
+pub const DMA_IRQ: u32 = 3;
@@ -0,0 +1,81 @@
+use crate::cpu::{DataWidth, Device, Memory};
+use std::any::Any;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Reads the current random value, which changes every tick
+const VALUE: u32 = 0x0;
+/// Writing a value reseeds the generator
+const SEED: u32 = 0x4;
+
+/// A pseudo-random number generator that steps once per tick. It's seeded from the host unless a
+/// seed is given, in which case the sequence is the same on every run.
+pub struct Random {
+    state: u64,
+    value: u32,
+}
+
+impl Random {
+    /// Creates a generator seeded from the host's randomness
+    pub fn new() -> Random {
+        Random::with_seed(RandomState::new().build_hasher().finish())
+    }
+
+    pub fn with_seed(seed: u64) -> Random {
+        let mut random = Random { state: 0, value: 0 };
+        random.reseed(seed);
+        random
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.state = seed;
+        self.step();
+    }
+
+    /// Advances the generator using SplitMix64
+    fn step(&mut self) {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        self.value = ((z ^ (z >> 31)) >> 32) as u32;
+    }
+}
+
+impl Default for Random {
+    fn default() -> Random {
+        Random::new()
+    }
+}
+
+impl Device for Random {
+    fn size(&self) -> u32 {
+        0x8
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        match offset {
+            VALUE => self.value,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, value: u32) {
+        if offset == SEED {
+            self.reseed(value as u64);
+        }
+    }
+
+    fn tick(&mut self, _memory: &mut Memory) -> bool {
+        self.step();
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,103 @@
+use crate::cpu::{DataWidth, Device, Memory};
+use std::any::Any;
+
+/// The counter, which counts up by one every tick while the timer is enabled
+const COUNT: u32 = 0x0;
+/// The value the counter is compared against
+const COMPARE: u32 = 0x4;
+/// A combination of the `CONTROL_*` bits
+const CONTROL: u32 = 0x8;
+/// Reads 1 if the counter has reached the compare value since the status was last cleared. Writing
+/// any value clears it.
+const STATUS: u32 = 0xc;
+/// The low word of the number of ticks since the computer started, which counts even while the
+/// timer is disabled
+const CYCLES_LOW: u32 = 0x10;
+const CYCLES_HIGH: u32 = 0x14;
+
+/// Counts up while set
+const CONTROL_ENABLE: u32 = 0b001;
+/// Requests an interrupt when the counter reaches the compare value
+const CONTROL_INTERRUPT: u32 = 0b010;
+/// Resets the counter to zero when it reaches the compare value, making the timer periodic
+const CONTROL_RESET: u32 = 0b100;
+
+/// A timer counting emulated ticks, so programs see the same timing no matter how fast the host
+/// runs them. Each tick is one instruction.
+pub struct Timer {
+    count: u32,
+    compare: u32,
+    control: u32,
+    expired: bool,
+    cycles: u64,
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            count: 0,
+            compare: 0,
+            control: 0,
+            expired: false,
+            cycles: 0,
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+impl Device for Timer {
+    fn size(&self) -> u32 {
+        0x18
+    }
+
+    fn read(&self, offset: u32, _width: DataWidth) -> u32 {
+        match offset {
+            COUNT => self.count,
+            COMPARE => self.compare,
+            CONTROL => self.control,
+            STATUS => self.expired as u32,
+            CYCLES_LOW => self.cycles as u32,
+            CYCLES_HIGH => (self.cycles >> 32) as u32,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, _width: DataWidth, value: u32) {
+        match offset {
+            COUNT => self.count = value,
+            COMPARE => self.compare = value,
+            CONTROL => self.control = value,
+            STATUS => self.expired = false,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, _memory: &mut Memory) -> bool {
+        self.cycles += 1;
+        if self.control & CONTROL_ENABLE == 0 {
+            return false;
+        }
+        self.count = self.count.wrapping_add(1);
+        if self.count != self.compare {
+            return false;
+        }
+        self.expired = true;
+        if self.control & CONTROL_RESET != 0 {
+            self.count = 0;
+        }
+        self.control & CONTROL_INTERRUPT != 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
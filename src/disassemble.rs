@@ -0,0 +1,66 @@
+//! Prints the instructions in a program image
+use rust_computer::asm;
+use rust_computer::cpu::Memory;
+
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: rust_computer_disasm [--start <address>] [--count <count>] <image>";
+
+pub fn main() {
+    let mut args = env::args().skip(1);
+    let mut image_path = None;
+    let mut start = 0;
+    let mut count = usize::MAX;
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--start" => parse_number(args.next()).map(|value| start = value as u32),
+            "--count" => parse_number(args.next()).map(|value| count = value as usize),
+            _ if image_path.is_none() && !arg.starts_with("--") => {
+                image_path = Some(arg.clone());
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid argument {}\n{}", arg, USAGE);
+            process::exit(2);
+        }
+    }
+    let image_path = image_path.unwrap_or_else(|| {
+        eprintln!("No image file specified\n{}", USAGE);
+        process::exit(2);
+    });
+
+    let image = fs::read(&image_path).unwrap_or_else(|err| {
+        eprintln!("Unable to read {}: {}", image_path, err);
+        process::exit(2);
+    });
+    let mut memory = Memory::new(image.len());
+    memory.ram_mut().load(&image);
+
+    for line in asm::disassemble_range(&memory, start, count) {
+        let start = line.address as usize;
+        let bytes: Vec<String> = image[start..start + line.length as usize]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        println!(
+            "{:08x}:  {:<24} {}",
+            line.address,
+            bytes.join(" "),
+            line.text
+        );
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal number
+fn parse_number(value: Option<String>) -> Option<u64> {
+    let value = value?;
+    if let Some(digits) = value.strip_prefix("0x") {
+        u64::from_str_radix(digits, 16).ok()
+    } else {
+        value.parse().ok()
+    }
+}
@@ -0,0 +1,205 @@
+//! Runs a program image without the UI, printing the final machine state once it stops. The
+//! console is connected to stdin and stdout.
+use rust_computer::devices::{self, audio, Audio, BlockDevice, Console};
+use rust_computer::{Computer, RunState};
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+const USAGE: &str = "Usage: rust_computer_run [--cycles <count>] [--memory <bytes>] \
+                     [--disk <file>] [--seed <seed>] [--time <seconds>] [--wav <file>] <image>";
+
+/// The exit status when the CPU faults
+const EXIT_FAULTED: i32 = 254;
+/// The exit status when the program is still running once `--cycles` instructions have executed
+const EXIT_CYCLE_LIMIT: i32 = 255;
+/// The highest exit status a `halt` code maps to. Exit statuses are only a byte on Unix, and the
+/// statuses above this are reserved for runs that didn't halt, so larger codes are capped here.
+const MAX_HALT_STATUS: u32 = 253;
+
+struct Options {
+    image: String,
+    /// the maximum number of instructions to execute before giving up
+    cycles: u64,
+    memory_size: usize,
+    /// a disk image to attach as the block device
+    disk: Option<String>,
+    /// runs the clock and random number generator deterministically from this seed
+    seed: Option<u64>,
+    /// the time the deterministic clock starts at, in seconds since the epoch
+    start_time: Option<u64>,
+    /// where to write the audio the program generates
+    wav: Option<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut image = None;
+        let mut cycles = 10_000_000;
+        let mut memory_size = 65536;
+        let mut disk = None;
+        let mut seed = None;
+        let mut start_time = None;
+        let mut wav = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--cycles" => cycles = parse_number(&arg, args.next())?,
+                "--memory" => memory_size = parse_number(&arg, args.next())?,
+                "--disk" => disk = Some(required(&arg, args.next())?),
+                "--seed" => seed = Some(parse_number(&arg, args.next())?),
+                "--time" => start_time = Some(parse_number(&arg, args.next())?),
+                "--wav" => wav = Some(required(&arg, args.next())?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if image.is_none() => image = Some(arg),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+        if start_time.is_some() && seed.is_none() {
+            return Err("--time requires --seed".to_string());
+        }
+        if memory_size > devices::MAX_RAM_SIZE {
+            return Err(format!(
+                "Memory size {} overlaps the devices, the most is {}",
+                memory_size,
+                devices::MAX_RAM_SIZE
+            ));
+        }
+
+        Ok(Options {
+            image: image.ok_or_else(|| "No image file specified".to_string())?,
+            cycles,
+            memory_size,
+            disk,
+            seed,
+            start_time,
+            wav,
+        })
+    }
+}
+
+fn required(option: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("Missing value for {}", option))
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = required(option, value)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", option, value))
+}
+
+pub fn main() {
+    let options = Options::parse(env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{}\n{}", message, USAGE);
+        process::exit(2);
+    });
+    let image = fs::read(&options.image).unwrap_or_else(|err| {
+        eprintln!("Unable to read {}: {}", options.image, err);
+        process::exit(2);
+    });
+
+    let mut computer = Computer::new(options.memory_size);
+    computer.load_image(&image);
+    if let Some(path) = &options.disk {
+        let disk = BlockDevice::open(path).unwrap_or_else(|err| {
+            eprintln!("Unable to open {}: {}", path, err);
+            process::exit(2);
+        });
+        computer.attach_disk(disk);
+    }
+    if let Some(seed) = options.seed {
+        computer.set_deterministic(seed, options.start_time.unwrap_or(0));
+    }
+    if options.wav.is_some() {
+        let audio = computer.memory.device_mut::<Audio>().unwrap();
+        audio.set_recording(true);
+    }
+
+    let input = spawn_stdin_reader();
+    let mut stdout = io::stdout();
+    // whether the last byte the program printed was a newline, so the summary starts on its own line
+    let mut at_line_start = true;
+    let mut cycles = 0;
+    while cycles < options.cycles {
+        cycles += 1;
+        let state = computer.tick();
+
+        let console = computer.memory.device_mut::<Console>().unwrap();
+        let output = console.take_output();
+        if let Some(&last) = output.last() {
+            stdout.write_all(&output).unwrap();
+            stdout.flush().unwrap();
+            at_line_start = last == b'\n';
+        }
+        for bytes in input.try_iter() {
+            console.push_input(&bytes);
+        }
+
+        if state != RunState::Running {
+            break;
+        }
+    }
+    if !at_line_start {
+        println!();
+    }
+
+    match computer.state {
+        RunState::Running => println!("Exit: cycle limit reached"),
+        state => println!("Exit: {}", state),
+    }
+    println!("Cycles: {}", cycles);
+    print_cpu(&computer);
+
+    if let Some(path) = &options.wav {
+        let audio = computer.memory.device_mut::<Audio>().unwrap();
+        let samples = audio.take_samples();
+        let result =
+            fs::File::create(path).and_then(|mut file| audio::write_wav(&mut file, &samples));
+        if let Err(err) = result {
+            eprintln!("Unable to write {}: {}", path, err);
+            process::exit(2);
+        }
+    }
+
+    match computer.state {
+        RunState::Halted(code) => process::exit(code.min(MAX_HALT_STATUS) as i32),
+        RunState::Faulted(_) => process::exit(EXIT_FAULTED),
+        RunState::Running => process::exit(EXIT_CYCLE_LIMIT),
+    }
+}
+
+/// Reads stdin on a separate thread so the program doesn't block waiting for input
+fn spawn_stdin_reader() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0; 256];
+        let mut stdin = io::stdin();
+        while let Ok(count) = stdin.read(&mut buffer) {
+            if count == 0 || sender.send(buffer[..count].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+fn print_cpu(computer: &Computer) {
+    println!("PC: 0x{:08x}", computer.cpu.program_counter.address);
+    println!("Flags: {}", computer.cpu.flags);
+    if let Some(frame) = computer.cpu.frames.last() {
+        println!("Registers:");
+        for (i, value) in frame.registers.iter().enumerate() {
+            println!("  R{:<2} 0x{:08x}", i, value);
+        }
+        println!("Vars:");
+        for (i, value) in frame.vars.iter().enumerate() {
+            println!("  {:<3} 0x{:08x}", i, value);
+        }
+    } else {
+        println!("No stack frame");
+    }
+}
@@ -0,0 +1,74 @@
+use super::super::devices::Framebuffer;
+use druid::piet::{ImageFormat, InterpolationMode};
+use druid::{
+    BoxConstraints, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Rect,
+    RenderContext, Size, UpdateCtx, Widget,
+};
+use std::sync::Arc;
+
+/// Paints the framebuffer's RGB pixels, scaled up as far as they fit while keeping their aspect
+/// ratio
+pub struct FramebufferWidget;
+
+impl Widget<Arc<Vec<u8>>> for FramebufferWidget {
+    fn event(&mut self, _ctx: &mut EventCtx, _event: &Event, _data: &mut Arc<Vec<u8>>, _env: &Env) {
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        _event: &LifeCycle,
+        _data: &Arc<Vec<u8>>,
+        _env: &Env,
+    ) {
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &Arc<Vec<u8>>,
+        data: &Arc<Vec<u8>>,
+        _env: &Env,
+    ) {
+        if !Arc::ptr_eq(old_data, data) {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _layout_ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Arc<Vec<u8>>,
+        _env: &Env,
+    ) -> Size {
+        let max_size = bc.max();
+        let scale = (max_size.width / Framebuffer::WIDTH as f64)
+            .min(max_size.height / Framebuffer::HEIGHT as f64);
+        Size {
+            width: Framebuffer::WIDTH as f64 * scale,
+            height: Framebuffer::HEIGHT as f64 * scale,
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Arc<Vec<u8>>, _env: &Env) {
+        // the UI hasn't read the framebuffer yet
+        if data.len() != Framebuffer::WIDTH * Framebuffer::HEIGHT * 3 {
+            return;
+        }
+        let size = ctx.size();
+        let image = ctx
+            .make_image(
+                Framebuffer::WIDTH,
+                Framebuffer::HEIGHT,
+                &data[..],
+                ImageFormat::Rgb,
+            )
+            .expect("unable to create the framebuffer image");
+        ctx.draw_image(
+            &image,
+            Rect::from_origin_size((0., 0.), size),
+            InterpolationMode::NearestNeighbor,
+        );
+    }
+}
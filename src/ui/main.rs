@@ -1,5 +1,9 @@
 use std::time::{Duration, Instant};
 
+use super::super::asm;
+use super::super::devices::keyboard::{self, Keyboard};
+use super::super::devices::{BlockDevice, Console, Framebuffer};
+use super::framebuffer::FramebufferWidget;
 use super::state::*;
 use super::worker::SimulatorHandle;
 use druid::lens::{self, LensExt};
@@ -8,9 +12,10 @@ use druid::widget::{
     WidgetExt,
 };
 use druid::{
-    AppLauncher, Color, Data, Env, Event, EventCtx, Key, KeyCode, Lens, LocalizedString,
-    RenderContext, Size, TimerToken, UnitPoint, Widget, WindowDesc,
+    AppLauncher, Color, Data, Env, Event, EventCtx, Key, KeyCode, KeyEvent, Lens, LifeCycle,
+    LifeCycleCtx, LocalizedString, RenderContext, Size, TimerToken, UnitPoint, Widget, WindowDesc,
 };
+use std::collections::HashSet;
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
@@ -56,8 +61,11 @@ pub fn setup_sim(sim_handle: &SimulatorHandle) {
     let mut f = File::open(&args[1]).expect("No file");
     let mut buffer = Vec::new();
     f.read_to_end(&mut buffer);
-    let limit = std::cmp::min(sim_state.computer.memory.data.len(), buffer.len());
-    sim_state.computer.memory.data[..limit].copy_from_slice(&buffer[..limit]);
+    sim_state.computer.load_image(&buffer);
+    if let Some(path) = args.get(2) {
+        let disk = BlockDevice::open(path).expect("Unable to open the disk image");
+        sim_state.computer.attach_disk(disk);
+    }
 
     let mut thread_state = sim_handle.thread_state.write().unwrap();
     thread_state.paused.set_paused(false);
@@ -65,6 +73,9 @@ pub fn setup_sim(sim_handle: &SimulatorHandle) {
 }
 
 const MONO_FONT: Key<&str> = Key::new("rust-computer.mono_font");
+/// how many lines of console output are kept
+const CONSOLE_LINES: usize = 200;
+const CONSOLE_HEIGHT: f64 = 200.;
 
 fn make_main_ui() -> impl Widget<AppData> {
     let controller = SimStateReader {
@@ -79,11 +90,16 @@ fn make_main_ui() -> impl Widget<AppData> {
                 .must_fill_main_axis(true)
                 .with_child(
                     Label::new(|data: &AppData, _env: &_| {
-                        format!("PC: 0x{:08x}", data.sim_state.cpu.program_counter)
+                        let cpu = &data.sim_state.cpu;
+                        format!("PC: 0x{:08x}  {}", cpu.program_counter, cpu.flags)
                     })
                     .with_font(MONO_FONT)
                     .padding(3.0),
                 )
+                .with_child(
+                    Label::new(|data: &AppData, _env: &_| data.sim_state.run_state.clone())
+                        .padding(3.0),
+                )
                 .with_child(
                     Label::new(|data: &AppData, _env: &_| {
                         format!("{:.2} Hz", data.sim_state.actual_frequency)
@@ -131,9 +147,58 @@ fn make_main_ui() -> impl Widget<AppData> {
                             .then(UiCpuState::vars),
                     ),
                     1.0,
+                )
+                .with_child(
+                    Label::new("Disassembly")
+                        .align_vertical(UnitPoint::LEFT)
+                        .padding(3.0),
+                )
+                .with_flex_child(
+                    Scroll::new(List::new(|| {
+                        Label::new(|item: &String, _env: &_| item.clone())
+                            .with_font(MONO_FONT)
+                            .align_vertical(UnitPoint::LEFT)
+                            .padding(3.0)
+                    }))
+                    .vertical()
+                    .lens(
+                        AppData::sim_state
+                            .then(UiSimState::cpu)
+                            .then(UiCpuState::disassembly),
+                    ),
+                    1.0,
                 ),
         )
-        .with_flex_spacer(1.)
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_flex_child(
+                    FramebufferWidget
+                        .padding(3.0)
+                        .lens(AppData::sim_state.then(UiSimState::framebuffer))
+                        .controller(MachineKeys {
+                            pressed: HashSet::new(),
+                        }),
+                    1.0,
+                )
+                .with_child(
+                    Label::new("Console")
+                        .align_vertical(UnitPoint::LEFT)
+                        .padding(3.0),
+                )
+                .with_child(
+                    Scroll::new(List::new(|| {
+                        Label::new(|item: &String, _env: &_| item.clone())
+                            .with_font(MONO_FONT)
+                            .align_vertical(UnitPoint::LEFT)
+                    }))
+                    .vertical()
+                    .fix_height(CONSOLE_HEIGHT)
+                    .padding(3.0)
+                    .lens(AppData::sim_state.then(UiSimState::console)),
+                ),
+            1.0,
+        )
         .background(BG)
         .controller(controller)
         .env_scope(|env: &mut druid::Env, data: &AppData| {
@@ -141,6 +206,145 @@ fn make_main_ui() -> impl Widget<AppData> {
         })
 }
 
+/// Appends console output to the displayed lines, dropping the oldest lines past `CONSOLE_LINES`
+fn append_console(console: &mut Arc<Vec<String>>, output: &[u8]) {
+    let lines = Arc::make_mut(console);
+    for c in String::from_utf8_lossy(output).chars() {
+        match c {
+            '\n' => lines.push(String::new()),
+            '\r' => {}
+            c => lines.last_mut().unwrap().push(c),
+        }
+    }
+    if lines.len() > CONSOLE_LINES {
+        let excess = lines.len() - CONSOLE_LINES;
+        lines.drain(..excess);
+    }
+}
+
+/// Queues a key event on the machine's keyboard
+fn send_key(data: &AppData, scancode: u8, character: Option<char>, released: bool) {
+    let mut sim_state = data.sim_handle.sim_state.write().unwrap();
+    let keyboard = sim_state.computer.memory.device_mut::<Keyboard>().unwrap();
+    keyboard.push(scancode, character, released);
+}
+
+/// Maps a key to the keyboard device's scancodes, or `None` if the machine doesn't have that key
+fn scancode(key: KeyCode) -> Option<u8> {
+    Some(match key {
+        KeyCode::KeyA => b'A',
+        KeyCode::KeyB => b'B',
+        KeyCode::KeyC => b'C',
+        KeyCode::KeyD => b'D',
+        KeyCode::KeyE => b'E',
+        KeyCode::KeyF => b'F',
+        KeyCode::KeyG => b'G',
+        KeyCode::KeyH => b'H',
+        KeyCode::KeyI => b'I',
+        KeyCode::KeyJ => b'J',
+        KeyCode::KeyK => b'K',
+        KeyCode::KeyL => b'L',
+        KeyCode::KeyM => b'M',
+        KeyCode::KeyN => b'N',
+        KeyCode::KeyO => b'O',
+        KeyCode::KeyP => b'P',
+        KeyCode::KeyQ => b'Q',
+        KeyCode::KeyR => b'R',
+        KeyCode::KeyS => b'S',
+        KeyCode::KeyT => b'T',
+        KeyCode::KeyU => b'U',
+        KeyCode::KeyV => b'V',
+        KeyCode::KeyW => b'W',
+        KeyCode::KeyX => b'X',
+        KeyCode::KeyY => b'Y',
+        KeyCode::KeyZ => b'Z',
+        KeyCode::Key0 => b'0',
+        KeyCode::Key1 => b'1',
+        KeyCode::Key2 => b'2',
+        KeyCode::Key3 => b'3',
+        KeyCode::Key4 => b'4',
+        KeyCode::Key5 => b'5',
+        KeyCode::Key6 => b'6',
+        KeyCode::Key7 => b'7',
+        KeyCode::Key8 => b'8',
+        KeyCode::Key9 => b'9',
+        KeyCode::Space => b' ',
+        KeyCode::Backspace => 0x08,
+        KeyCode::Tab => b'\t',
+        KeyCode::Return => b'\n',
+        KeyCode::Escape => 0x1b,
+        KeyCode::Delete => 0x7f,
+        KeyCode::ArrowUp => keyboard::KEY_UP,
+        KeyCode::ArrowDown => keyboard::KEY_DOWN,
+        KeyCode::ArrowLeft => keyboard::KEY_LEFT,
+        KeyCode::ArrowRight => keyboard::KEY_RIGHT,
+        KeyCode::LeftShift | KeyCode::RightShift => keyboard::KEY_SHIFT,
+        KeyCode::LeftControl | KeyCode::RightControl => keyboard::KEY_CONTROL,
+        KeyCode::LeftAlt | KeyCode::RightAlt => keyboard::KEY_ALT,
+        _ => return None,
+    })
+}
+
+/// The character a key event typed, if any
+fn character(event: &KeyEvent) -> Option<char> {
+    event.text().and_then(|text| text.chars().next())
+}
+
+/// Feeds key events to the machine's keyboard while the framebuffer has focus. Clicking the
+/// framebuffer focuses it, and clicking anywhere else hands the keys back to the simulation
+/// controls.
+struct MachineKeys {
+    /// the keys the machine has seen pressed, so it's only sent releases for those
+    pressed: HashSet<u8>,
+}
+
+impl<W: Widget<AppData>> Controller<AppData, W> for MachineKeys {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppData,
+        env: &Env,
+    ) {
+        match event {
+            Event::MouseDown(_) => ctx.request_focus(),
+            Event::KeyDown(e) if ctx.is_focused() => {
+                if let Some(scancode) = scancode(e.key_code) {
+                    self.pressed.insert(scancode);
+                    send_key(data, scancode, character(e), false);
+                }
+            }
+            Event::KeyUp(e) if ctx.is_focused() => {
+                if let Some(scancode) = scancode(e.key_code) {
+                    if self.pressed.remove(&scancode) {
+                        send_key(data, scancode, character(e), true);
+                    }
+                }
+            }
+            _ => (),
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppData,
+        env: &Env,
+    ) {
+        // the releases for any held keys go to the simulation controls, so release them here
+        if let LifeCycle::FocusChanged(false) = event {
+            for scancode in self.pressed.drain() {
+                send_key(data, scancode, None, true);
+            }
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
 struct SimStateReader {
     timer_id: TimerToken,
     ui_ups: f64,
@@ -167,12 +371,29 @@ impl Controller<AppData, Container<AppData>> for SimStateReader {
                         let mut thread_state = data.sim_handle.thread_state.write().unwrap();
                         thread_state.ui_frequency = self.ui_ups;
                         data.sim_state.actual_frequency = thread_state.actual_frequency;
+                        data.sim_state.run_state = thread_state.run_state.to_string();
+                    }
+
+                    {
+                        let mut sim_state = data.sim_handle.sim_state.write().unwrap();
+                        let console = sim_state.computer.memory.device_mut::<Console>().unwrap();
+                        let output = console.take_output();
+                        if !output.is_empty() {
+                            append_console(&mut data.sim_state.console, &output);
+                        }
+                    }
+
+                    {
+                        let sim_state = data.sim_handle.sim_state.read().unwrap();
+                        let framebuffer = sim_state.computer.memory.device::<Framebuffer>();
+                        data.sim_state.framebuffer = Arc::new(framebuffer.unwrap().to_rgb());
                     }
 
                     {
                         let sim_state = data.sim_handle.sim_state.read().unwrap();
                         data.sim_state.cpu.program_counter =
                             sim_state.computer.cpu.program_counter.address;
+                        data.sim_state.cpu.flags = sim_state.computer.cpu.flags.to_string();
                         {
                             let vars: &mut Vec<(usize, u32)> =
                                 Arc::make_mut(&mut data.sim_state.cpu.registers);
@@ -191,12 +412,24 @@ impl Controller<AppData, Container<AppData>> for SimStateReader {
                                 vars.clone_from(&frame.vars);
                             }
                         }
+                        {
+                            let lines: &mut Vec<String> =
+                                Arc::make_mut(&mut data.sim_state.cpu.disassembly);
+                            lines.clear();
+                            let computer = &sim_state.computer;
+                            let pc = computer.cpu.program_counter.address;
+                            for line in asm::disassemble_range(&computer.memory, pc, 8) {
+                                lines.push(format!("{:08x}  {}", line.address, line.text));
+                            }
+                        }
                     }
                     let deadline = Instant::now() + Duration::from_secs_f64(1. / self.ui_ups);
                     self.timer_id = ctx.request_timer(deadline);
                 }
             }
-            Event::KeyDown(e) => {
+            Event::MouseDown(_) => ctx.request_focus(),
+            // key events only reach here without focus when the framebuffer has it
+            Event::KeyDown(e) if ctx.is_focused() => {
                 if e.key_code == KeyCode::Space && !e.is_repeat {
                     let did_pause: bool;
                     {
@@ -207,7 +440,7 @@ impl Controller<AppData, Container<AppData>> for SimStateReader {
                     if did_pause {
                         let sim_state = data.sim_handle.sim_state.read().unwrap();
                         let mut f = File::create("debug/memory.bin").unwrap();
-                        f.write_all(&sim_state.computer.memory.data[..]);
+                        f.write_all(&sim_state.computer.memory.ram().data[..]);
                     }
                 }
                 if e.key_code == KeyCode::Period {
@@ -220,9 +453,10 @@ impl Controller<AppData, Container<AppData>> for SimStateReader {
                         .is_paused()
                     {
                         let mut sim_state = data.sim_handle.sim_state.write().unwrap();
-                        sim_state.computer.tick();
+                        let run_state = sim_state.computer.tick();
+                        data.sim_handle.thread_state.write().unwrap().run_state = run_state;
                         let mut f = File::create("debug/memory.bin").unwrap();
-                        f.write_all(&sim_state.computer.memory.data[..]);
+                        f.write_all(&sim_state.computer.memory.ram().data[..]);
                     }
                 }
             }
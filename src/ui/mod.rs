@@ -1,3 +1,4 @@
+mod framebuffer;
 mod game_of_life;
 mod main;
 mod state;
@@ -5,13 +5,22 @@ use std::sync::Arc;
 pub struct UiSimState {
     pub cpu: UiCpuState,
     pub actual_frequency: f64,
+    pub run_state: String,
+    /// the lines printed to the console, including the unfinished last line
+    pub console: Arc<Vec<String>>,
+    /// the framebuffer's pixels as RGB triples
+    pub framebuffer: Arc<Vec<u8>>,
 }
 
 #[derive(Clone, Data, Lens)]
 pub struct UiCpuState {
     pub program_counter: u32,
+    /// the status flags, formatted as `CVZN`
+    pub flags: String,
     pub registers: Arc<Vec<(usize, u32)>>,
     pub vars: Arc<Vec<u32>>,
+    /// the instructions starting at the program counter
+    pub disassembly: Arc<Vec<String>>,
 }
 
 impl UiSimState {
@@ -19,10 +28,15 @@ impl UiSimState {
         UiSimState {
             cpu: UiCpuState {
                 program_counter: 0,
+                flags: String::new(),
                 registers: Arc::new(vec![]),
                 vars: Arc::new(vec![]),
+                disassembly: Arc::new(vec![]),
             },
             actual_frequency: 0.,
+            run_state: String::new(),
+            console: Arc::new(vec![String::new()]),
+            framebuffer: Arc::new(vec![]),
         }
     }
 }
@@ -2,7 +2,7 @@ use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 // use crate::Computer;
-use super::super::Computer; // crate:: doesn't seem to work with vscode. possibly related to rust issue#69933?
+use super::super::{Computer, RunState}; // crate:: doesn't seem to work with vscode. possibly related to rust issue#69933?
 use super::utils::*;
 use spin_sleep::LoopHelper;
 
@@ -17,6 +17,8 @@ pub struct SimulatorThreadState {
     /// how many times the UI updates every second
     pub ui_frequency: f64,
     pub paused: Arc<PauseState>,
+    /// the state of the computer as of the last batch of ticks
+    pub run_state: RunState,
 }
 pub struct SimulatorState {
     pub computer: Computer,
@@ -29,6 +31,7 @@ impl SimulatorHandle {
             ui_frequency: 1.,
             actual_frequency: 0.,
             paused: Arc::new(PauseState::new(true)),
+            run_state: RunState::Running,
         }));
         let sim_state = Arc::new(RwLock::new(SimulatorState {
             computer: Computer::new(65536),
@@ -88,10 +91,25 @@ fn run_simulation(
             loop_helper.set_target_rate(target_frequency / updates_per_frame as f64);
         }
 
+        let run_state: RunState;
         {
             let mut sim_state = sim_state_lock.write().unwrap();
+            let mut state = sim_state.computer.state;
             for _ in 0..updates_per_frame {
-                sim_state.computer.tick();
+                state = sim_state.computer.tick();
+                if state != RunState::Running {
+                    break;
+                }
+            }
+            run_state = state;
+        }
+
+        {
+            let mut thread_state = thread_state_lock.write().unwrap();
+            thread_state.run_state = run_state;
+            if run_state != RunState::Running {
+                // there's nothing left to execute, so wait until the UI unpauses us
+                thread_state.paused.set_paused(true);
             }
         }
 
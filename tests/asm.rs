@@ -0,0 +1,171 @@
+use rust_computer::asm::{assemble, disassemble_range, AsmError};
+use rust_computer::cpu::Memory;
+
+/// Assembles a single `move` and returns the bytes of its source operand
+fn source_operand(operand: &str) -> Vec<u8> {
+    let bytes = assemble(&format!("move {}, r0", operand)).unwrap();
+    assert_eq!(bytes[0], 0x01, "`{}` isn't a move", operand);
+    assert_eq!(
+        bytes[bytes.len() - 1],
+        0xc0,
+        "`{}` isn't moved to r0",
+        operand
+    );
+    bytes[1..bytes.len() - 1].to_vec()
+}
+
+fn error(source: &str) -> AsmError {
+    match assemble(source) {
+        Ok(bytes) => panic!("expected an error but got {:02x?}", bytes),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn operand_encodings() {
+    let cases: &[(&str, &[u8])] = &[
+        // immediates below 0x80 are stored in the location byte itself
+        ("5", &[0x05]),
+        ("'a'", &[0x61]),
+        ("200", &[0x80, 0xc8]),
+        ("0x1234", &[0x81, 0x12, 0x34]),
+        ("0x12345678", &[0x82, 0x12, 0x34, 0x56, 0x78]),
+        ("0b1010_1010", &[0x80, 0xaa]),
+        ("5.w", &[0x82, 0x00, 0x00, 0x00, 0x05]),
+        ("5.s", &[0x81, 0x00, 0x05]),
+        ("-1", &[0x82, 0xff, 0xff, 0xff, 0xff]),
+        ("1.5", &[0x82, 0x3f, 0xc0, 0x00, 0x00]),
+        ("-1.5", &[0x82, 0xbf, 0xc0, 0x00, 0x00]),
+        ("r7", &[0xc7]),
+        ("frame[3]", &[0x84, 0x03]),
+        ("frame[300]", &[0x85, 0x01, 0x2c]),
+        ("[r2]", &[0x8a, 0x02]),
+        ("[r2].b", &[0x88, 0x02]),
+        ("[frame[1]].s", &[0x8d, 0x00, 0x00, 0x00, 0x01]),
+        ("[r3]+.b", &[0x90, 0x03]),
+        ("-[r3]", &[0x9a, 0x03]),
+        ("-[frame[2]].b", &[0x9c, 0x00, 0x00, 0x00, 0x02]),
+    ];
+    for (operand, expected) in cases {
+        assert_eq!(source_operand(operand), *expected, "`{}`", operand);
+    }
+}
+
+#[test]
+fn forward_references_settle() {
+    // the first pass assumes `end` needs a full word, which moves `end` itself until the passes
+    // agree that it fits in the location byte
+    assert_eq!(
+        assemble("move end, r0\nend:\nhalt").unwrap(),
+        [0x01, 0x03, 0xc0, 0x02]
+    );
+    assert_eq!(
+        assemble("jmp end\nnop\nend: halt").unwrap(),
+        [0x10, 0x00, 0x00, 0x00, 0x06, 0x00, 0x02]
+    );
+}
+
+#[test]
+fn constants_and_expressions() {
+    assert_eq!(
+        assemble("base = 0x100\nsize = base + 0x20 - 1\n#d16 size").unwrap(),
+        [0x01, 0x1f]
+    );
+}
+
+#[test]
+fn local_labels_are_scoped() {
+    let bytes = assemble(
+        "first:
+        .loop: jmp .loop
+        second:
+        .loop: jmp .loop",
+    )
+    .unwrap();
+    assert_eq!(
+        bytes,
+        [0x10, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x05]
+    );
+    assert_eq!(error("nop\n.loop: jmp .loop").line, 2);
+}
+
+#[test]
+fn data_directives() {
+    assert_eq!(
+        assemble("#d8 1, 'a'\n#d16 0x1234\n#d32 label\n#str \"hi\\n\"\nlabel:").unwrap(),
+        [1, b'a', 0x12, 0x34, 0, 0, 0, 11, b'h', b'i', b'\n']
+    );
+    assert_eq!(assemble("halt\n#addr 4\nhalt").unwrap(), [2, 0, 0, 0, 2]);
+}
+
+#[test]
+fn errors_report_their_line() {
+    let cases = &[
+        ("nop\nfoo r0", 2, "unknown instruction `foo`"),
+        ("nop\n\njmp missing", 3, "undefined symbol `missing`"),
+        ("a:\na:", 2, "`a` is already defined"),
+        (
+            "move 5, 6",
+            1,
+            "an immediate can't be used as a destination",
+        ),
+        (
+            "halt 1, 2",
+            1,
+            "`halt` takes 0 or 1 operands but 2 were given",
+        ),
+        ("#d8 256", 1, "0x100 doesn't fit in a byte"),
+        (
+            "#d8 0, 0\n#addr 1",
+            2,
+            "`#addr` can't move backwards from 0x2 to 0x1",
+        ),
+        ("move r16, r0", 1, "invalid register `r16`"),
+        (
+            "#addr 0xfff00000",
+            1,
+            "`#addr` 0xfff00000 isn't below the end of RAM at 0xfff00000",
+        ),
+    ];
+    for (source, line, message) in cases {
+        assert_eq!(
+            error(source),
+            AsmError::new(*line, message.to_string()),
+            "{:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn disassembly_reassembles() {
+    let source = "start:
+            move 0x12345678, r0
+            add [r1]+.b, frame[300], -[frame[2]].s
+            fadd 1.5, r2, [r3]
+            jne r0, 200, start
+            callf 4, start
+            ei
+            ret
+            halt 0x1234";
+    let image = assemble(source).unwrap();
+    let mut memory = Memory::new(image.len());
+    memory.ram_mut().load(&image);
+    let lines = disassemble_range(&memory, 0, usize::MAX);
+    let text: Vec<_> = lines.iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(assemble(&text.join("\n")).unwrap(), image);
+}
+
+#[test]
+fn unassemblable_instructions_disassemble_as_data() {
+    // `move [r200].b, r0` decodes, but the assembler only knows registers up to r15
+    let mut memory = Memory::new(5);
+    memory.ram_mut().load(&[0x01, 0x88, 0xc8, 0xc0, 0x02]);
+    let lines = disassemble_range(&memory, 0, usize::MAX);
+    let text: Vec<_> = lines.iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(
+        text,
+        ["#d8 0x01", "#d8 0x88", "#d8 0xc8", "#d8 0xc0", "halt"]
+    );
+    assert_eq!(assemble(&text.join("\n")).unwrap(), memory.ram().data);
+}
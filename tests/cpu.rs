@@ -0,0 +1,428 @@
+use rust_computer::asm::assemble;
+use rust_computer::cpu::{CpuPanic, DataWidth, Flags, PanicKind, MAX_FRAMES, MAX_STACK_VARS};
+use rust_computer::{Computer, RunState};
+
+/// Assembles and runs a program until it stops, giving up after a fixed number of instructions
+fn run(source: &str) -> Computer {
+    let mut computer = Computer::new(1024);
+    computer.load_image(&assemble(source).unwrap());
+    for _ in 0..10_000 {
+        if computer.tick() != RunState::Running {
+            break;
+        }
+    }
+    computer
+}
+
+#[test]
+fn divide_by_zero_faults() {
+    for mnemonic in &["div", "rem", "idiv", "irem"] {
+        let source = format!("move 0, r1\n{} 10, r1, r0\nhalt", mnemonic);
+        let computer = run(&source);
+        assert_eq!(
+            computer.state,
+            RunState::Faulted(CpuPanic {
+                pc: 3,
+                kind: PanicKind::DivideByZero
+            }),
+            "`{}` didn't fault",
+            mnemonic
+        );
+    }
+}
+
+#[test]
+fn divide_by_nonzero_succeeds() {
+    let computer = run("div 10, 3, r0\nrem 10, 3, r1\nidiv -7, 2, r2\nhalt");
+    assert_eq!(computer.state, RunState::Halted(0));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(registers[0], 3);
+    assert_eq!(registers[1], 1);
+    assert_eq!(registers[2], -3i32 as u32);
+}
+
+#[test]
+fn fault_handler_recovers() {
+    let computer = run("fhandler handler
+        div 1, 0, r0
+        halt 1
+    recovered:
+        halt 0
+    handler:
+        jne r0, 10, fail
+        jne r1, 2, fail
+        reti recovered
+    fail:
+        halt 2");
+    assert_eq!(computer.state, RunState::Halted(0));
+    assert_eq!(computer.cpu.frames.len(), 1);
+}
+
+#[test]
+fn jump_to_end_of_address_space_faults() {
+    let computer = run("jmp 0xffffffff");
+    assert_eq!(
+        computer.state,
+        RunState::Faulted(CpuPanic {
+            pc: 0xffffffff,
+            kind: PanicKind::OutOfBounds {
+                address: 0xffffffff,
+                width: DataWidth::Byte
+            }
+        })
+    );
+}
+
+#[test]
+fn calls_pass_r0_back() {
+    let computer = run("   move 1, r0
+            move 7, r1
+            callf 2, double
+            halt r0
+        double:
+            move r1, frame[1]
+            add frame[1], frame[1], r0
+            move 99, r1             ; the caller's registers aren't touched
+            ret");
+    assert_eq!(computer.state, RunState::Halted(14));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(registers[1], 7);
+}
+
+#[test]
+fn deep_recursion_overflows() {
+    let computer = run("recurse: call recurse");
+    assert_eq!(
+        computer.state,
+        RunState::Faulted(CpuPanic {
+            pc: 0,
+            kind: PanicKind::StackOverflow
+        })
+    );
+    assert_eq!(computer.cpu.frames.len(), MAX_FRAMES);
+
+    // each frame is allowed its vars, but the whole stack isn't allowed that many
+    let computer = run("recurse: callf 65536, recurse");
+    assert_eq!(
+        computer.state,
+        RunState::Faulted(CpuPanic {
+            pc: 0,
+            kind: PanicKind::StackOverflow
+        })
+    );
+    let vars: usize = computer
+        .cpu
+        .frames
+        .iter()
+        .map(|frame| frame.vars.len())
+        .sum();
+    assert!(vars <= MAX_STACK_VARS);
+}
+
+#[test]
+fn return_from_outermost_frame_underflows() {
+    let computer = run("ret");
+    assert_eq!(
+        computer.state,
+        RunState::Faulted(CpuPanic {
+            pc: 0,
+            kind: PanicKind::StackUnderflow
+        })
+    );
+}
+
+#[test]
+fn shifts_by_32_or_more() {
+    let computer = run("   move 0x80000000, r0
+            sar r0, 4, r1
+            sar r0, 40, r2
+            sar 0x40000000, 40, r3
+            shl 1, 32, r4
+            shr r0, 32, r5
+            rol 0x80000001, 33, r6
+            ror 0x80000001, 36, r7
+            shr r0, 31, r8
+            halt");
+    assert_eq!(computer.state, RunState::Halted(0));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(
+        registers[1..9],
+        [0xf8000000, 0xffffffff, 0, 0, 0, 0x00000003, 0x18000000, 1]
+    );
+}
+
+#[test]
+fn float_conversions_saturate() {
+    let computer = run("   ftoi 3000000000.0, r0
+            ftoi -3000000000.0, r1
+            ftoi -2.7, r2
+            ftou -5.0, r3
+            ftou 5000000000.0, r4
+            fdiv 0.0, 0.0, r5
+            ftoi r5, r6
+            ftou r5, r7
+            halt");
+    assert_eq!(computer.state, RunState::Halted(0));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(
+        registers[..5],
+        [0x7fffffff, 0x80000000, -2i32 as u32, 0, 0xffffffff]
+    );
+    assert!(f32::from_bits(registers[5]).is_nan());
+    assert_eq!(registers[6..8], [0, 0]);
+}
+
+#[test]
+fn nan_is_only_unequal() {
+    let computer = run("   fdiv 0.0, 0.0, r0
+            fjeq r0, r0, fail
+            fjlt r0, 1.0, fail
+            fjge r0, 1.0, fail
+            fjne r0, r0, pass
+        fail:
+            halt 1
+        pass:
+            halt 0");
+    assert_eq!(computer.state, RunState::Halted(0));
+}
+
+/// Runs a single instruction and returns the flags it set
+fn flags_after(instruction: &str) -> Flags {
+    let computer = run(&format!("{}\nhalt", instruction));
+    assert_eq!(computer.state, RunState::Halted(0), "`{}`", instruction);
+    computer.cpu.flags
+}
+
+#[test]
+fn arithmetic_sets_flags() {
+    let cases = &[
+        ("add 0xffffffff, 1, r0", "C-Z-"),
+        ("add 0x7fffffff, 1, r0", "-V-N"),
+        ("add 0x80000000, 0x80000000, r0", "CVZ-"),
+        ("sub 1, 2, r0", "C--N"),
+        ("sub 0x80000000, 1, r0", "-V--"),
+        ("sub 5, 5, r0", "--Z-"),
+        ("mul 0x10000, 0x10000, r0", "CVZ-"),
+        ("mul -1, -1, r0", "C---"),
+        ("and 0x80000000, 0xffffffff, r0", "---N"),
+        ("div 0xffffffff, 1, r0", "---N"),
+    ];
+    for (instruction, flags) in cases {
+        assert_eq!(
+            flags_after(instruction).to_string(),
+            *flags,
+            "`{}`",
+            instruction
+        );
+    }
+}
+
+#[test]
+fn carry_chains_across_words() {
+    // 0x1_ffffffff + 1 and 0x2_00000000 - 1, a word at a time
+    let computer = run("   add 0xffffffff, 1, r0
+            adc 1, 0, r1
+            sub 0, 1, r2
+            sbb 2, 0, r3
+            add 1, 1, r4
+            adc 1, 1, r5            ; no carry in
+            halt");
+    assert_eq!(computer.state, RunState::Halted(0));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(registers[..6], [0, 2, 0xffffffff, 1, 2, 2]);
+}
+
+#[test]
+fn flag_jumps() {
+    let mnemonics = ["jc", "jnc", "jo", "jno", "jz", "jnz", "jn", "jnn"];
+    let cases = &[
+        ("add 0x7fffffff, 1, r15", [0, 1, 1, 0, 0, 1, 1, 0]),
+        ("add 0xffffffff, 1, r15", [1, 0, 0, 1, 1, 0, 0, 1]),
+    ];
+    for (setup, expected) in cases {
+        // each jump records whether it was taken in its own register, since `move` keeps the flags
+        let mut source = format!("{}\n", setup);
+        for (i, mnemonic) in mnemonics.iter().enumerate() {
+            source += &format!(
+                "{m} taken{i}\nmove 0, r{i}\njmp next{i}\ntaken{i}: move 1, r{i}\nnext{i}:\n",
+                m = mnemonic,
+                i = i
+            );
+        }
+        source += "halt";
+        let computer = run(&source);
+        assert_eq!(computer.state, RunState::Halted(0));
+        let registers = &computer.cpu.frames.last().unwrap().registers;
+        assert_eq!(registers[..8], expected[..], "after `{}`", setup);
+    }
+}
+
+#[test]
+fn faults_undo_address_updates() {
+    // the first operand's increment has happened by the time the second one faults
+    let computer = run("   move 0, r3
+            move 2000, r5
+            add [r3]+, -[r5], r4");
+    assert!(matches!(computer.state, RunState::Faulted(_)));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(registers[3..6], [0, 0, 2000]);
+
+    let computer = run("   callf 2, sub
+        sub:
+            move 2000, frame[1]
+            move [frame[0]]+, [frame[1]]+");
+    assert!(matches!(computer.state, RunState::Faulted(_)));
+    assert_eq!(computer.cpu.frames.last().unwrap().vars, [0, 2000]);
+
+    // the handler sees the registers as they were before the faulting instruction
+    let computer = run("   fhandler handler
+            move 2000, r3
+            move [r3]+, r4
+            halt 1
+        handler:
+            jne r3, 2000, fail
+            halt 0
+        fail:
+            halt 2");
+    assert_eq!(computer.state, RunState::Halted(0));
+}
+
+#[test]
+fn signed_division_and_remainder() {
+    let computer = run("   idiv 7, -2, r0
+            irem 7, -2, r1
+            irem -7, 2, r2
+            irem -7, -2, r3
+            idiv 0x80000000, -1, r4 ; the one quotient that doesn't fit wraps
+            irem 0x80000000, -1, r5
+            halt");
+    assert_eq!(computer.state, RunState::Halted(0));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(
+        registers[..6],
+        [-3i32 as u32, 1, -1i32 as u32, -1i32 as u32, 0x80000000, 0]
+    );
+}
+
+#[test]
+fn sign_extension() {
+    let computer = run("   sextb 0x80, r0
+            sextb 0x17f, r1
+            sexts 0x8000, r2
+            sexts 0x12345, r3
+            halt");
+    assert_eq!(computer.state, RunState::Halted(0));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(registers[..4], [0xffffff80, 0x7f, 0xffff8000, 0x2345]);
+
+    assert_eq!(flags_after("sextb 0x80, r0").to_string(), "---N");
+    assert_eq!(flags_after("sexts 0x10000, r0").to_string(), "--Z-");
+}
+
+#[test]
+fn signed_jumps() {
+    let cases = &[
+        ("-1, 1", [1, 1, 0, 0]),
+        ("1, 1", [0, 1, 1, 0]),
+        ("1, -1", [0, 0, 1, 1]),
+        ("0x80000000, 0x7fffffff", [1, 1, 0, 0]),
+    ];
+    for (operands, expected) in cases {
+        for (mnemonic, taken) in ["ijlt", "ijle", "ijge", "ijgt"].iter().zip(expected) {
+            let instruction = format!("{} {}, taken", mnemonic, operands);
+            let computer = run(&format!("{}\nhalt 0\ntaken: halt 1", instruction));
+            assert_eq!(
+                computer.state,
+                RunState::Halted(*taken),
+                "`{}`",
+                instruction
+            );
+        }
+    }
+}
+
+#[test]
+fn interrupts_run_their_handler() {
+    let computer = run("   ivt table
+            ei
+            sub 0, 1, r1            ; sets carry and negative for the handler to clobber
+            int 3
+            jnc fail
+            jnn fail
+            int 3                   ; `reti` enabled interrupts again
+            move count, r0
+            halt [r0]
+        fail:
+            halt 100
+        handler:
+            move count, r0
+            add [r0], 1, [r0]
+            reti
+        count:
+            #d32 0
+        table:
+            #d32 0, 0, 0, handler");
+    assert_eq!(computer.state, RunState::Halted(2));
+    assert!(computer.cpu.interrupts.enabled);
+    assert_eq!(computer.cpu.frames.len(), 1);
+}
+
+#[test]
+fn masked_and_disabled_interrupts_stay_pending() {
+    let computer = run("   ivt table
+            move count, r0
+            imask 0b0111
+            ei
+            int 3
+            jne [r0], 0, fail
+            di
+            imask 0b1000
+            jne [r0], 0, fail
+            ei
+            halt [r0]
+        fail:
+            halt 100
+        handler:
+            move count, r0
+            add [r0], 1, [r0]
+            reti
+        count:
+            #d32 0
+        table:
+            #d32 0, 0, 0, handler");
+    assert_eq!(computer.state, RunState::Halted(1));
+    assert_eq!(computer.cpu.interrupts.pending, 0);
+}
+
+#[test]
+fn invalid_interrupt_faults() {
+    let computer = run("int 32");
+    assert_eq!(
+        computer.state,
+        RunState::Faulted(CpuPanic {
+            pc: 0,
+            kind: PanicKind::InvalidInterrupt(32)
+        })
+    );
+}
+
+#[test]
+fn interrupt_survives_a_bad_vector() {
+    let computer = run("   fhandler fixup
+            ivt 0x10000             ; outside RAM
+            ei
+            int 3
+            halt 1
+        fixup:
+            ivt table
+            reti
+        handler:
+            halt 0
+        table:
+            #d32 0, 0, 0, handler");
+    assert_eq!(computer.state, RunState::Halted(0));
+
+    let computer = run("ivt 0x10000\nei\nint 3\nhalt");
+    assert!(matches!(computer.state, RunState::Faulted(_)));
+    assert_eq!(computer.cpu.interrupts.pending, 1 << 3);
+}
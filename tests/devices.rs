@@ -0,0 +1,345 @@
+use rust_computer::asm::assemble;
+use rust_computer::devices::audio::{self, CHANNELS};
+use rust_computer::devices::block::{SECTORS_PER_TICK, SECTOR_SIZE};
+use rust_computer::devices::dma::MAX_RATE;
+use rust_computer::devices::{Audio, BlockDevice, Console, Framebuffer, Keyboard};
+use rust_computer::{Computer, RunState};
+
+/// Assembles a program and runs it for up to `cycles` instructions
+fn run(computer: &mut Computer, source: &str, cycles: usize) -> RunState {
+    computer.load_image(&assemble(source).unwrap());
+    for _ in 0..cycles {
+        if computer.tick() != RunState::Running {
+            break;
+        }
+    }
+    computer.state
+}
+
+#[test]
+fn console_upper_cases_input() {
+    let mut computer = Computer::new(1024);
+    let console = computer.memory.device_mut::<Console>().unwrap();
+    console.push_input(b"hi");
+    let state = run(
+        &mut computer,
+        "   move 0xffff0000, r1
+            move 0xffff0004, r2
+            move 0xffff0008, r3
+        loop:
+            jeq [r3], 0, done
+            sub [r2], 32, [r1].b
+            move 0, [r2]
+            jmp loop
+        done:
+            halt",
+        100,
+    );
+    assert_eq!(state, RunState::Halted(0));
+    let console = computer.memory.device_mut::<Console>().unwrap();
+    assert_eq!(console.take_output(), b"HI");
+}
+
+#[test]
+fn timer_interrupts_periodically() {
+    let mut computer = Computer::new(1024);
+    let state = run(
+        &mut computer,
+        "   ivt vectors
+            move 0xffff0104, r1
+            move 20, [r1]           ; compare
+            move 0xffff0108, r1
+            move 0b111, [r1]        ; enable, interrupt and reset
+            move count, r2
+            ei
+        wait:
+            jlt [r2], 5, wait
+            move 0xffff0110, r1
+            halt [r1]               ; exit with the number of ticks so far
+        handler:
+            add [r2], 1, [r2]
+            move 0xffff010c, r3
+            move 0, [r3]            ; acknowledge
+            reti
+        count:
+        #d32 0
+        vectors:
+        #d32 handler",
+        1000,
+    );
+    // the timer counts emulated ticks, so the timing is exactly the same on every run
+    assert_eq!(state, RunState::Halted(110));
+}
+
+#[test]
+fn framebuffer_uses_palette() {
+    let mut computer = Computer::new(1024);
+    let state = run(
+        &mut computer,
+        "   move 0xfff00000, r1
+            move 5, [r1].b          ; first pixel uses palette entry 5
+            move 0xfff0c014, r1
+            move 0x123456, [r1]     ; palette entry 5
+            halt",
+        100,
+    );
+    assert_eq!(state, RunState::Halted(0));
+    let rgb = computer.memory.device::<Framebuffer>().unwrap().to_rgb();
+    assert_eq!(rgb.len(), Framebuffer::WIDTH * Framebuffer::HEIGHT * 3);
+    assert_eq!(rgb[..6], [0x12, 0x34, 0x56, 0, 0, 0]);
+}
+
+#[test]
+fn keyboard_interrupts_on_key_press() {
+    let mut computer = Computer::new(1024);
+    let state = run(
+        &mut computer,
+        "   ivt vectors
+            move 0xffff0208, r1
+            move 1, [r1]            ; interrupt on key events
+            ei
+        wait:
+            jmp wait
+        handler:
+            move 0xffff0200, r1
+            halt [r1]
+        vectors:
+        #d32 0, handler",
+        50,
+    );
+    assert_eq!(state, RunState::Running);
+    let keyboard = computer.memory.device_mut::<Keyboard>().unwrap();
+    keyboard.push(b'A', Some('a'), false);
+    for _ in 0..10 {
+        computer.tick();
+    }
+    assert_eq!(
+        computer.state,
+        RunState::Halted((b'A' as u32) << 16 | 'a' as u32)
+    );
+}
+
+/// A disk image path that concurrent test runs won't share
+fn disk_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("rust_computer_{}_{}.img", name, std::process::id()))
+}
+
+#[test]
+fn block_device_transfers_sectors() {
+    let path = disk_path("transfers");
+    let mut image = vec![0; SECTOR_SIZE * 2];
+    image[SECTOR_SIZE] = 42;
+    std::fs::write(&path, &image).unwrap();
+
+    let mut computer = Computer::new(2048);
+    computer.attach_disk(BlockDevice::open(&path).unwrap());
+    let state = run(
+        &mut computer,
+        "   move 0xffff0300, r1     ; sector
+            move 0xffff0304, r2     ; address
+            move 0xffff0308, r3     ; count
+            move 0xffff030c, r4     ; command
+            move 0xffff0310, r5     ; status
+            move 1, [r1]
+            move 0x400, [r2]
+            move 1, [r3]
+            move 1, [r4]            ; read sector 1 to 0x400
+        wait_read:
+            jeq [r5], 1, wait_read
+            jne [r5], 0, fail
+            move 0, [r1]
+            move 0, [r2]
+            move 2, [r4]            ; write the start of memory to sector 0
+        wait_write:
+            jeq [r5], 1, wait_write
+            jne [r5], 0, fail
+            move 0x400, r6
+            halt [r6].b
+        fail:
+            halt 100",
+        200,
+    );
+    let written = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(state, RunState::Halted(42));
+    assert_eq!(written[..4], computer.memory.ram().data[..4]);
+}
+
+#[test]
+fn block_device_spreads_large_transfers_over_ticks() {
+    let path = disk_path("large");
+    let sectors = SECTORS_PER_TICK as usize * 3 + 1;
+    let image: Vec<u8> = (0..SECTOR_SIZE * sectors)
+        .map(|i| (i / SECTOR_SIZE) as u8)
+        .collect();
+    std::fs::write(&path, &image).unwrap();
+
+    let mut computer = Computer::new(SECTOR_SIZE * (sectors + 1));
+    computer.attach_disk(BlockDevice::open(&path).unwrap());
+    let source = format!(
+        "   move 0xffff0304, r1
+            move {}, [r1]           ; address
+            move 0xffff0308, r1
+            move {}, [r1]           ; count
+            move 0xffff030c, r1
+            move 1, [r1]            ; read
+            move 0xffff0310, r1
+        wait:
+            add r2, 1, r2           ; count the polls
+            jeq [r1], 1, wait
+            halt [r1]",
+        SECTOR_SIZE, sectors
+    );
+    let state = run(&mut computer, &source, 200);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(state, RunState::Halted(0));
+    assert_eq!(computer.memory.ram().data[SECTOR_SIZE..], image[..]);
+    // the transfer was still running the first time the status was polled
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert!(registers[2] > 1);
+}
+
+#[test]
+fn dma_fills_and_copies_to_devices() {
+    let mut computer = Computer::new(1024);
+    let state = run(
+        &mut computer,
+        "   ivt vectors
+            move 0xffff0400, r1     ; source
+            move 0xffff0404, r2     ; dest
+            move 0xffff0408, r3     ; length
+            move 0xffff040c, r4     ; value
+            move 0xffff0410, r5     ; rate
+            move 0xffff0414, r6     ; command
+            move 0xffff041c, r7     ; control
+            move 0x300, [r2]
+            move 8, [r3]
+            move 0xaa, [r4]
+            move 3, [r5]
+            move 2, [r6]            ; fill
+        wait_fill:
+            jne [r3], 0, wait_fill
+            move message, [r1]
+            move 0xffff0000, [r2]   ; the console's output register
+            move 5, [r3]
+            move 0b101, [r7]        ; interrupt when done, fixed destination
+            ei
+            move 1, [r6]            ; copy
+        wait_copy:
+            jmp wait_copy
+        handler:
+            halt
+        message:
+        #str \"hello\"
+        vectors:
+        #d32 0, 0, 0, handler",
+        200,
+    );
+    assert_eq!(state, RunState::Halted(0));
+    let ram = &computer.memory.ram().data;
+    assert_eq!(ram[0x300..0x308], [0xaa; 8]);
+    assert_eq!(ram[0x308], 0);
+    let console = computer.memory.device_mut::<Console>().unwrap();
+    assert_eq!(console.take_output(), b"hello");
+}
+
+#[test]
+fn dma_rate_is_clamped() {
+    let mut computer = Computer::new(0x4000);
+    let state = run(
+        &mut computer,
+        "   move 0xffff0404, r2     ; dest
+            move 0xffff0408, r3     ; length
+            move 0xffff0410, r5     ; rate
+            move 0xffff0414, r6     ; command
+            move 0x1000, [r2]
+            move 0x2000, [r3]
+            move 0xffffffff, [r5]
+            move [r5], r8
+            move 2, [r6]            ; fill
+            move [r3], r9
+        wait:
+            jne [r3], 0, wait
+            halt",
+        100,
+    );
+    assert_eq!(state, RunState::Halted(0));
+    let registers = &computer.cpu.frames.last().unwrap().registers;
+    assert_eq!(registers[8], MAX_RATE);
+    // one tick's worth had been filled when the length was read back
+    assert_eq!(registers[9], 0x2000 - MAX_RATE);
+}
+
+#[test]
+fn deterministic_mode_is_reproducible() {
+    let source = "  move 0xffff0500, r1
+            move 0, [r1]            ; latch the clock
+            move 0xffff0504, r1
+            move [r1], r2           ; seconds
+            move 0xffff0600, r1
+            move [r1], r3
+            move [r1], r4
+            jeq r3, r4, fail        ; the value changes every tick
+            jne r2, 1000, fail
+            halt r3
+        fail:
+            halt 0";
+    let mut results = Vec::new();
+    for _ in 0..2 {
+        let mut computer = Computer::new(1024);
+        computer.set_deterministic(7, 1000);
+        results.push(run(&mut computer, source, 100));
+    }
+    assert_ne!(results[0], RunState::Halted(0));
+    assert_eq!(results[0], results[1]);
+}
+
+#[test]
+fn audio_renders_square_wave() {
+    let mut computer = Computer::new(1024);
+    computer
+        .memory
+        .device_mut::<Audio>()
+        .unwrap()
+        .set_recording(true);
+    run(
+        &mut computer,
+        "   move 0xffff0700, r1
+            move 441, [r1]+         ; a period of 100 samples
+            move 255, [r1]
+        loop:
+            jmp loop",
+        // 10ms of emulated time
+        10_000,
+    );
+    let samples = computer
+        .memory
+        .device_mut::<Audio>()
+        .unwrap()
+        .take_samples();
+    assert_eq!(samples.len(), 441);
+    // the wave starts a couple of samples in, after the registers are written
+    let high = i16::MAX / CHANNELS as i16;
+    assert!(samples[2..50].iter().all(|&sample| sample == high));
+    assert!(samples[53..100].iter().all(|&sample| sample == -high));
+
+    let mut wav = Vec::new();
+    audio::write_wav(&mut wav, &samples).unwrap();
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(wav.len(), 44 + samples.len() * 2);
+}
+
+#[test]
+fn deterministic_clock_saturates() {
+    let mut computer = Computer::new(1024);
+    computer.set_deterministic(u64::MAX, u64::MAX);
+    let state = run(
+        &mut computer,
+        "   move 0xffff0500, r1
+            move 0, [r1]            ; latch the clock
+            move 0xffff050c, r1
+            halt [r1]               ; milliseconds",
+        100,
+    );
+    assert_eq!(state, RunState::Halted((u64::MAX % 1000) as u32));
+}